@@ -0,0 +1,233 @@
+//! Aggregates computed per node rather than per element, for use with
+//! [`ChthollyTree::aggregate`](crate::ChthollyTree::aggregate) and
+//! [`CachedTree`](crate::CachedTree)
+
+use std::ops::BitXor;
+
+use num_traits::{Num, NumCast, Zero};
+
+use crate::Monoid;
+
+/// An aggregate over the elements of a tree that knows how to summarise a whole node
+pub trait RunAggregate<T> {
+    type Output;
+
+    fn identity() -> Self::Output;
+    /// the aggregate of `len` copies of `value`
+    fn from_run(len: usize, value: &T) -> Self::Output;
+    fn combine(acc: Self::Output, other: Self::Output) -> Self::Output;
+}
+
+impl<T, A> Monoid<T> for A
+where
+    A: RunAggregate<T>,
+    A::Output: Clone,
+{
+    type Output = A::Output;
+
+    fn identity() -> Self::Output {
+        A::identity()
+    }
+
+    fn combine(a: &Self::Output, b: &Self::Output) -> Self::Output {
+        A::combine(a.clone(), b.clone())
+    }
+
+    fn pow(value: &T, run_len: usize) -> Self::Output {
+        A::from_run(run_len, value)
+    }
+}
+
+/// the sum of the elements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Sum;
+
+/// the product of the elements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Product;
+
+/// the smallest element, `None` if there are no elements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Min;
+
+/// the largest element, `None` if there are no elements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Max;
+
+/// the number of elements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Count;
+
+/// the bitwise xor of the elements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Xor;
+
+/// the non-negative greatest common divisor of the elements, zero if there are none
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Gcd;
+
+impl<T: Num + NumCast + Clone> RunAggregate<T> for Sum {
+    type Output = T;
+
+    fn identity() -> T {
+        T::zero()
+    }
+
+    fn from_run(len: usize, value: &T) -> T {
+        T::from(len).unwrap() * value.clone()
+    }
+
+    fn combine(acc: T, other: T) -> T {
+        acc + other
+    }
+}
+
+impl<T: Num + Clone> RunAggregate<T> for Product {
+    type Output = T;
+
+    fn identity() -> T {
+        T::one()
+    }
+
+    fn from_run(len: usize, value: &T) -> T {
+        num_traits::pow(value.clone(), len)
+    }
+
+    fn combine(acc: T, other: T) -> T {
+        acc * other
+    }
+}
+
+impl<T: Ord + Clone> RunAggregate<T> for Min {
+    type Output = Option<T>;
+
+    fn identity() -> Option<T> {
+        None
+    }
+
+    fn from_run(_len: usize, value: &T) -> Option<T> {
+        Some(value.clone())
+    }
+
+    fn combine(acc: Option<T>, other: Option<T>) -> Option<T> {
+        match (acc, other) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+impl<T: Ord + Clone> RunAggregate<T> for Max {
+    type Output = Option<T>;
+
+    fn identity() -> Option<T> {
+        None
+    }
+
+    fn from_run(_len: usize, value: &T) -> Option<T> {
+        Some(value.clone())
+    }
+
+    fn combine(acc: Option<T>, other: Option<T>) -> Option<T> {
+        match (acc, other) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+impl<T> RunAggregate<T> for Count {
+    type Output = usize;
+
+    fn identity() -> usize {
+        0
+    }
+
+    fn from_run(len: usize, _value: &T) -> usize {
+        len
+    }
+
+    fn combine(acc: usize, other: usize) -> usize {
+        acc + other
+    }
+}
+
+impl<T: BitXor<Output = T> + Zero + Clone> RunAggregate<T> for Xor {
+    type Output = T;
+
+    fn identity() -> T {
+        T::zero()
+    }
+
+    fn from_run(len: usize, value: &T) -> T {
+        if len % 2 == 1 {
+            value.clone()
+        } else {
+            T::zero()
+        }
+    }
+
+    fn combine(acc: T, other: T) -> T {
+        acc ^ other
+    }
+}
+
+impl<T: Num + PartialOrd + Clone> RunAggregate<T> for Gcd {
+    type Output = T;
+
+    fn identity() -> T {
+        T::zero()
+    }
+
+    fn from_run(_len: usize, value: &T) -> T {
+        if *value < T::zero() {
+            T::zero() - value.clone()
+        } else {
+            value.clone()
+        }
+    }
+
+    fn combine(mut acc: T, mut other: T) -> T {
+        while !other.is_zero() {
+            let rem = acc % other.clone();
+            acc = other;
+            other = rem;
+        }
+        acc
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Count, Gcd, Max, Min, Product, Sum, Xor};
+    use crate::{CachedTree, ChthollyTree};
+
+    #[test]
+    fn aggregates() {
+        let data = [6, 6, -4, 10, 10, 10, 2, 8];
+        let tree = ChthollyTree::from_iter(data);
+        assert_eq!(tree.aggregate::<Sum>(1..7), data[1..7].iter().sum());
+        assert_eq!(tree.aggregate::<Product>(..), data.iter().product());
+        assert_eq!(tree.aggregate::<Min>(3..), Some(2));
+        assert_eq!(tree.aggregate::<Max>(..3), Some(6));
+        assert_eq!(tree.aggregate::<Min>(3..3), None);
+        assert_eq!(tree.aggregate::<Count>(2..=5), 4);
+        assert_eq!(
+            tree.aggregate::<Xor>(..),
+            data.iter().fold(0, |acc, x| acc ^ x)
+        );
+        assert_eq!(tree.aggregate::<Gcd>(..), 2);
+        assert_eq!(tree.aggregate::<Gcd>(3..6), 10);
+        assert_eq!(tree.aggregate::<Gcd>(2..3), 4);
+    }
+
+    #[test]
+    fn as_monoid() {
+        let data = [6, 6, -4, 10, 10, 10, 2, 8];
+        let mut tree = CachedTree::<_, Max>::from(ChthollyTree::from_iter(data));
+        assert_eq!(tree.query(..), Some(10));
+        tree.assign(0, 3..6);
+        assert_eq!(tree.query(2..), Some(8));
+        assert_eq!(tree.query(..=5), Some(6));
+    }
+}
@@ -0,0 +1,84 @@
+use num_traits::{
+    AsPrimitive, Bounded, CheckedAdd, CheckedMul, NumCast, SaturatingAdd, SaturatingMul,
+    WrappingAdd, WrappingMul, Zero,
+};
+
+/// How arithmetic on tree values handles overflow
+///
+/// A policy returns `None` to report an overflow, which makes the calling method fail
+/// without modifying the tree.
+pub trait ArithmeticPolicy<T> {
+    fn add(a: &T, b: &T) -> Option<T>;
+    fn mul(a: &T, b: &T) -> Option<T>;
+    /// the sum of `n` copies of `val`, which may overflow even if `n` does not fit in `T`
+    fn repeat(n: usize, val: &T) -> Option<T>;
+}
+
+/// fails on overflow
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Checked;
+
+/// wraps around on overflow
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Wrapping;
+
+/// saturates at the numeric bounds on overflow
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Saturating;
+
+impl<T: CheckedAdd + CheckedMul + NumCast + Zero> ArithmeticPolicy<T> for Checked {
+    fn add(a: &T, b: &T) -> Option<T> {
+        a.checked_add(b)
+    }
+
+    fn mul(a: &T, b: &T) -> Option<T> {
+        a.checked_mul(b)
+    }
+
+    fn repeat(n: usize, val: &T) -> Option<T> {
+        if val.is_zero() {
+            return Some(T::zero());
+        }
+        T::from(n)?.checked_mul(val)
+    }
+}
+
+impl<T> ArithmeticPolicy<T> for Wrapping
+where
+    T: WrappingAdd + WrappingMul + Copy + 'static,
+    usize: AsPrimitive<T>,
+{
+    fn add(a: &T, b: &T) -> Option<T> {
+        Some(a.wrapping_add(b))
+    }
+
+    fn mul(a: &T, b: &T) -> Option<T> {
+        Some(a.wrapping_mul(b))
+    }
+
+    fn repeat(n: usize, val: &T) -> Option<T> {
+        Some(n.as_().wrapping_mul(val))
+    }
+}
+
+impl<T> ArithmeticPolicy<T> for Saturating
+where
+    T: SaturatingAdd + SaturatingMul + NumCast + Bounded + Zero + PartialOrd,
+{
+    fn add(a: &T, b: &T) -> Option<T> {
+        Some(a.saturating_add(b))
+    }
+
+    fn mul(a: &T, b: &T) -> Option<T> {
+        Some(a.saturating_mul(b))
+    }
+
+    fn repeat(n: usize, val: &T) -> Option<T> {
+        Some(match T::from(n) {
+            Some(n) => n.saturating_mul(val),
+            None if val.is_zero() => T::zero(),
+            None if *val > T::zero() => T::max_value(),
+            None => T::min_value(),
+        })
+    }
+}
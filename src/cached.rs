@@ -0,0 +1,347 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, Range, RangeBounds};
+
+use crate::ChthollyTree;
+
+/// An associative operation with an identity, used by [`CachedTree`] to aggregate ranges
+pub trait Monoid<T> {
+    type Output: Clone;
+
+    fn identity() -> Self::Output;
+    fn combine(a: &Self::Output, b: &Self::Output) -> Self::Output;
+    /// the aggregate of a node holding `run_len` copies of `value`
+    fn pow(value: &T, run_len: usize) -> Self::Output;
+}
+
+/// A [`ChthollyTree`] that caches the aggregate of a [`Monoid`] over every subtree of its
+/// nodes, so that [`query`](Self::query) costs O(log n) however fragmented the tree is
+///
+/// Each mutation rebuilds the cached entries of the nodes it touched, which costs
+/// O(k log n) for k such nodes. Read-only access to the tree goes through `Deref`.
+pub struct CachedTree<T, M: Monoid<T>> {
+    tree: ChthollyTree<T>,
+    cache: Cache<M::Output>,
+    _monoid: PhantomData<M>,
+}
+
+impl<T, M: Monoid<T>> CachedTree<T, M> {
+    pub fn new() -> Self {
+        Self {
+            tree: ChthollyTree::new(),
+            cache: Cache::default(),
+            _monoid: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> ChthollyTree<T> {
+        self.tree
+    }
+
+    /// aggregates the elements in `range`, returning the identity for an empty range
+    pub fn query(&self, range: impl RangeBounds<usize>) -> M::Output {
+        let (l, r) = match self.tree.bounds(range) {
+            Some(rg) => rg,
+            _ => return M::identity(),
+        };
+
+        let (first, val) = self.tree.run_at(l).unwrap();
+        if first.end >= r {
+            return M::pow(val, r - l);
+        }
+        let (last, last_val) = self.tree.run_at(r - 1).unwrap();
+        let acc = M::combine(
+            &M::pow(val, first.end - l),
+            &self.cache.fold::<T, M>(first.end, last.start),
+        );
+        M::combine(&acc, &M::pow(last_val, r - last.start))
+    }
+
+    /// rebuilds the cached entries of the nodes starting in `[lo, hi]`
+    fn resync(&mut self, lo: usize, hi: usize) {
+        let (left, rest) = Cache::split::<T, M>(self.cache.root.take(), lo);
+        let (_, right) = Cache::split::<T, M>(rest, hi + 1);
+        let mut mid = None;
+        for (&l, (r, val)) in self.tree.inner.range(lo..=hi) {
+            let node = self.cache.node(l, M::pow(val, r - l));
+            mid = Cache::merge::<T, M>(mid, Some(node));
+        }
+        let left = Cache::merge::<T, M>(left, mid);
+        self.cache.root = Cache::merge::<T, M>(left, right);
+    }
+
+    /// the start of the node containing position `at`
+    fn run_start(&self, at: usize) -> usize {
+        self.tree.run_at(at).map_or(at, |(run, _)| run.start)
+    }
+}
+
+impl<T, M: Monoid<T>> Default for CachedTree<T, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug, M: Monoid<T>> fmt::Debug for CachedTree<T, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedTree")
+            .field("tree", &self.tree)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, M: Monoid<T>> Deref for CachedTree<T, M> {
+    type Target = ChthollyTree<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tree
+    }
+}
+
+impl<T, M: Monoid<T>> From<ChthollyTree<T>> for CachedTree<T, M> {
+    fn from(tree: ChthollyTree<T>) -> Self {
+        let mut cached = Self {
+            tree,
+            cache: Cache::default(),
+            _monoid: PhantomData,
+        };
+        cached.resync(0, cached.tree.len());
+        cached
+    }
+}
+
+impl<T: Eq, M: Monoid<T>> CachedTree<T, M> {
+    pub fn push(&mut self, value: T) {
+        self.tree.push(value);
+        let at = self.run_start(self.tree.len() - 1);
+        self.resync(at, at);
+    }
+}
+
+impl<T: Clone, M: Monoid<T>> CachedTree<T, M> {
+    /// see [`ChthollyTree::split`]
+    pub fn split(&mut self, at: usize) {
+        self.tree.split(at);
+        if at > 0 {
+            let lo = self.run_start(at - 1);
+            self.resync(lo, at);
+        }
+    }
+
+    /// see [`ChthollyTree::assign`]
+    pub fn assign(&mut self, val: T, range: impl RangeBounds<usize>) {
+        self.update(range, |tree, rg| tree.assign(val, rg));
+    }
+
+    /// see [`ChthollyTree::map_range`]
+    pub fn map_range(&mut self, f: impl Fn(&mut T), range: impl RangeBounds<usize>) {
+        self.update(range, |tree, rg| tree.map_range(f, rg));
+    }
+
+    fn update(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        f: impl FnOnce(&mut ChthollyTree<T>, Range<usize>),
+    ) {
+        if let Some((rg, starts)) = self.tree.affected(range) {
+            f(&mut self.tree, rg);
+            let (lo, hi) = starts.into_inner();
+            self.resync(lo, hi);
+        }
+    }
+}
+
+type Link<A> = Option<Box<Node<A>>>;
+
+/// a treap keyed by node start, holding the aggregate of every subtree
+struct Node<A> {
+    key: usize,
+    priority: u64,
+    own: A,
+    agg: A,
+    left: Link<A>,
+    right: Link<A>,
+}
+
+struct Cache<A> {
+    root: Link<A>,
+    seed: u64,
+}
+
+impl<A> Default for Cache<A> {
+    fn default() -> Self {
+        Self {
+            root: None,
+            seed: 0,
+        }
+    }
+}
+
+impl<A: Clone> Cache<A> {
+    fn node(&mut self, key: usize, own: A) -> Box<Node<A>> {
+        // splitmix64
+        self.seed = self.seed.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        Box::new(Node {
+            key,
+            priority: z ^ (z >> 31),
+            agg: own.clone(),
+            own,
+            left: None,
+            right: None,
+        })
+    }
+
+    fn agg<T, M: Monoid<T, Output = A>>(link: &Link<A>) -> A {
+        link.as_ref()
+            .map_or_else(M::identity, |node| node.agg.clone())
+    }
+
+    fn pull<T, M: Monoid<T, Output = A>>(mut node: Box<Node<A>>) -> Box<Node<A>> {
+        let left = M::combine(&Self::agg::<T, M>(&node.left), &node.own);
+        node.agg = M::combine(&left, &Self::agg::<T, M>(&node.right));
+        node
+    }
+
+    /// splits into the keys less than `key` and the rest
+    fn split<T, M: Monoid<T, Output = A>>(link: Link<A>, key: usize) -> (Link<A>, Link<A>) {
+        match link {
+            None => (None, None),
+            Some(mut node) if node.key < key => {
+                let (l, r) = Self::split::<T, M>(node.right.take(), key);
+                node.right = l;
+                (Some(Self::pull::<T, M>(node)), r)
+            }
+            Some(mut node) => {
+                let (l, r) = Self::split::<T, M>(node.left.take(), key);
+                node.left = r;
+                (l, Some(Self::pull::<T, M>(node)))
+            }
+        }
+    }
+
+    fn merge<T, M: Monoid<T, Output = A>>(a: Link<A>, b: Link<A>) -> Link<A> {
+        match (a, b) {
+            (None, b) => b,
+            (a, None) => a,
+            (Some(mut a), Some(b)) if a.priority > b.priority => {
+                a.right = Self::merge::<T, M>(a.right.take(), Some(b));
+                Some(Self::pull::<T, M>(a))
+            }
+            (a, Some(mut b)) => {
+                b.left = Self::merge::<T, M>(a, b.left.take());
+                Some(Self::pull::<T, M>(b))
+            }
+        }
+    }
+
+    /// aggregates the nodes with keys in `lo..hi`
+    fn fold<T, M: Monoid<T, Output = A>>(&self, lo: usize, hi: usize) -> A {
+        Self::fold_link::<T, M>(&self.root, Some(lo), Some(hi))
+    }
+
+    /// `None` bounds are unbounded, in which case every node on that side is covered
+    fn fold_link<T, M: Monoid<T, Output = A>>(
+        link: &Link<A>,
+        lo: Option<usize>,
+        hi: Option<usize>,
+    ) -> A {
+        let node = match link {
+            Some(node) => node,
+            None => return M::identity(),
+        };
+        match (lo, hi) {
+            (None, None) => node.agg.clone(),
+            (Some(lo), _) if node.key < lo => Self::fold_link::<T, M>(&node.right, Some(lo), hi),
+            (_, Some(hi)) if node.key >= hi => Self::fold_link::<T, M>(&node.left, lo, Some(hi)),
+            _ => {
+                let left = Self::fold_link::<T, M>(&node.left, lo, None);
+                let right = Self::fold_link::<T, M>(&node.right, None, hi);
+                M::combine(&M::combine(&left, &node.own), &right)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CachedTree, Monoid};
+    use crate::test::Rng;
+    use crate::ChthollyTree;
+
+    struct Sum;
+
+    impl Monoid<i64> for Sum {
+        type Output = i64;
+
+        fn identity() -> i64 {
+            0
+        }
+
+        fn combine(a: &i64, b: &i64) -> i64 {
+            a + b
+        }
+
+        fn pow(value: &i64, run_len: usize) -> i64 {
+            value * run_len as i64
+        }
+    }
+
+    /// the sequence itself, to check that `combine` keeps the order
+    struct Concat;
+
+    impl Monoid<i64> for Concat {
+        type Output = Vec<i64>;
+
+        fn identity() -> Vec<i64> {
+            vec![]
+        }
+
+        fn combine(a: &Vec<i64>, b: &Vec<i64>) -> Vec<i64> {
+            a.iter().chain(b).copied().collect()
+        }
+
+        fn pow(value: &i64, run_len: usize) -> Vec<i64> {
+            vec![*value; run_len]
+        }
+    }
+
+    #[test]
+    fn query() {
+        let mut sum = CachedTree::<_, Sum>::from(ChthollyTree::from_iter([1, 1, 2, 3, 3, 3]));
+        let mut seq = CachedTree::<_, Concat>::from(ChthollyTree::from_iter([1, 1, 2, 3, 3, 3]));
+        let mut model = vec![1, 1, 2, 3, 3, 3];
+        let mut rng = Rng(0x94d049bb133111eb);
+        for _ in 0..300 {
+            let bounds = rng.valid_bounds(model.len());
+            let val = rng.below(5) as i64;
+            match rng.below(4) {
+                0 => {
+                    sum.assign(val, bounds);
+                    seq.assign(val, bounds);
+                    model[bounds].iter_mut().for_each(|x| *x = val);
+                }
+                1 => {
+                    sum.map_range(|x| *x += val, bounds);
+                    seq.map_range(|x| *x += val, bounds);
+                    model[bounds].iter_mut().for_each(|x| *x += val);
+                }
+                2 => {
+                    let at = rng.below(model.len() + 1);
+                    sum.split(at);
+                    seq.split(at);
+                }
+                _ => {
+                    sum.push(val);
+                    seq.push(val);
+                    model.push(val);
+                }
+            }
+            assert_eq!(sum.query(bounds), model[bounds].iter().sum());
+            assert_eq!(seq.query(bounds), model[bounds]);
+            assert_eq!(seq.query(..), model);
+        }
+    }
+}
@@ -0,0 +1,351 @@
+//! A compact, dependency-free binary encoding of trees
+//!
+//! The layout is
+//!
+//! - the magic bytes `CHTL` and a version byte
+//! - the number of nodes as a LEB128 varint
+//! - for every node, its length as a LEB128 varint followed by its value encoded by
+//!   [`RunCodec`]
+//! - a CRC-32 of everything above, little-endian
+//!
+//! so the size depends on the number of nodes rather than on the length of the tree.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+use crate::ChthollyTree;
+
+const MAGIC: &[u8; 4] = b"CHTL";
+const VERSION: u8 = 1;
+
+/// Encodes and decodes single values of a tree
+pub trait RunCodec: Sized {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()>;
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self>;
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// writes `value` as an unsigned LEB128 varint
+pub fn write_varint<W: Write>(w: &mut W, mut value: u128) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// reads an unsigned LEB128 varint
+pub fn read_varint<R: Read>(r: &mut R) -> io::Result<u128> {
+    let mut value = 0u128;
+    for shift in (0..128).step_by(7) {
+        let mut byte = [0];
+        r.read_exact(&mut byte)?;
+        let bits = (byte[0] & 0x7f) as u128;
+        if shift > 0 && bits >> (128 - shift).min(7) != 0 {
+            return Err(invalid_data("varint overflows u128"));
+        }
+        value |= bits << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("varint overflows u128"))
+}
+
+macro_rules! impl_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl RunCodec for $ty {
+                fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+                    write_varint(w, *self as u128)
+                }
+
+                fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+                    <$ty>::try_from(read_varint(r)?)
+                        .map_err(|_| invalid_data(concat!("value out of range for ", stringify!($ty))))
+                }
+            }
+        )*
+    };
+}
+
+/// signed integers are zigzag encoded, so that small negative values stay short
+macro_rules! impl_signed {
+    ($($ty:ty),*) => {
+        $(
+            impl RunCodec for $ty {
+                fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+                    let v = *self as i128;
+                    write_varint(w, ((v << 1) ^ (v >> 127)) as u128)
+                }
+
+                fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+                    let v = read_varint(r)?;
+                    let v = (v >> 1) as i128 ^ -((v & 1) as i128);
+                    <$ty>::try_from(v)
+                        .map_err(|_| invalid_data(concat!("value out of range for ", stringify!($ty))))
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned!(u8, u16, u32, u64, u128, usize);
+impl_signed!(i8, i16, i32, i64, i128, isize);
+
+impl RunCodec for bool {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as u8).encode(w)
+    }
+
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        match u8::decode(r)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("invalid bool")),
+        }
+    }
+}
+
+impl RunCodec for char {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as u32).encode(w)
+    }
+
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        char::from_u32(u32::decode(r)?).ok_or_else(|| invalid_data("invalid char"))
+    }
+}
+
+impl RunCodec for String {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.len().encode(w)?;
+        w.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        let len = usize::decode(r)?;
+        let mut bytes = vec![];
+        r.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(|_| invalid_data("invalid utf-8"))
+    }
+}
+
+/// CRC-32 (IEEE 802.3)
+struct Crc32(u32);
+
+impl Crc32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 == 1 {
+                    0xedb88320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+
+    fn new() -> Self {
+        Self(!0)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = Self::TABLE[((self.0 ^ b as u32) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+/// checksums everything written through it
+struct CrcWriter<W> {
+    inner: W,
+    crc: Crc32,
+}
+
+impl<W: Write> Write for CrcWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// checksums everything read through it
+struct CrcReader<R> {
+    inner: R,
+    crc: Crc32,
+}
+
+impl<R: Read> Read for CrcReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<T: RunCodec> ChthollyTree<T> {
+    /// writes the tree in the format described in the [module documentation](self)
+    pub fn encode(&self, w: impl Write) -> io::Result<()> {
+        let mut w = CrcWriter {
+            inner: w,
+            crc: Crc32::new(),
+        };
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        write_varint(&mut w, self.inner.len() as u128)?;
+        for (run, val) in self.runs() {
+            write_varint(&mut w, run.len() as u128)?;
+            val.encode(&mut w)?;
+        }
+        let crc = w.crc.finish();
+        w.inner.write_all(&crc.to_le_bytes())
+    }
+
+    /// reads a tree written by [`encode`](Self::encode)
+    ///
+    /// fails with [`io::ErrorKind::InvalidData`] on a bad header, an invalid node or a
+    /// checksum mismatch, and with [`io::ErrorKind::UnexpectedEof`] on truncated input
+    pub fn decode(r: impl Read) -> io::Result<Self> {
+        let mut r = CrcReader {
+            inner: r,
+            crc: Crc32::new(),
+        };
+        let mut header = [0; 5];
+        r.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid_data("not an encoded tree"));
+        }
+        if header[4] != VERSION {
+            return Err(invalid_data("unsupported version"));
+        }
+
+        let nodes = read_varint(&mut r)?;
+        let mut inner = BTreeMap::new();
+        let mut len = 0usize;
+        for _ in 0..nodes {
+            let count = usize::try_from(read_varint(&mut r)?)
+                .map_err(|_| invalid_data("node length overflows usize"))?;
+            if count == 0 {
+                return Err(invalid_data("empty node"));
+            }
+            let val = T::decode(&mut r)?;
+            let end = len
+                .checked_add(count)
+                .ok_or_else(|| invalid_data("total length overflows usize"))?;
+            inner.insert(len, (end, val));
+            len = end;
+        }
+
+        let crc = r.crc.finish();
+        let mut expected = [0; 4];
+        r.inner.read_exact(&mut expected)?;
+        if crc != u32::from_le_bytes(expected) {
+            return Err(invalid_data("checksum mismatch"));
+        }
+        Ok(Self { inner, len })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Cursor};
+
+    use super::{read_varint, write_varint, Crc32, RunCodec};
+    use crate::ChthollyTree;
+
+    fn round_trip<T: RunCodec + PartialEq>(tree: &ChthollyTree<T>) {
+        let mut buf = vec![];
+        tree.encode(&mut buf).unwrap();
+        let decoded = ChthollyTree::<T>::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.len(), tree.len());
+        assert!(decoded.runs().eq(tree.runs()));
+    }
+
+    #[test]
+    fn varint() {
+        for v in [0, 1, 127, 128, 300, u64::MAX as u128, u128::MAX] {
+            let mut buf = vec![];
+            write_varint(&mut buf, v).unwrap();
+            assert_eq!(read_varint(&mut Cursor::new(buf)).unwrap(), v);
+        }
+        let too_long = [0xff; 19]
+            .iter()
+            .chain(&[0x7f])
+            .copied()
+            .collect::<Vec<_>>();
+        assert!(read_varint(&mut too_long.as_slice()).is_err());
+    }
+
+    #[test]
+    fn crc32() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xcbf43926);
+    }
+
+    #[test]
+    fn encode_decode() {
+        let mut tree = ChthollyTree::from_iter([0u8]);
+        tree.insert_run(1, 1_000_000_000, 7);
+        tree.assign(3, 500..=1000);
+        let mut buf = vec![];
+        tree.encode(&mut buf).unwrap();
+        assert!(buf.len() < 32);
+        round_trip(&tree);
+
+        round_trip(&ChthollyTree::from_iter([-3i64, -3, i64::MIN, 5, i64::MAX]));
+        round_trip(&ChthollyTree::from_iter(
+            ["a", "a", "", "ß"].map(String::from),
+        ));
+        round_trip(&ChthollyTree::from_iter(['x', 'y', 'y']));
+        round_trip(&ChthollyTree::<bool>::new());
+    }
+
+    #[test]
+    fn corrupted() {
+        let tree = ChthollyTree::from_iter([1u32, 1, 2, 300, 300]);
+        let mut buf = vec![];
+        tree.encode(&mut buf).unwrap();
+
+        for i in 0..buf.len() {
+            let mut bad = buf.clone();
+            bad[i] ^= 0x10;
+            assert!(ChthollyTree::<u32>::decode(bad.as_slice()).is_err());
+        }
+        for len in 0..buf.len() {
+            let err = ChthollyTree::<u32>::decode(&buf[..len]).unwrap_err();
+            assert!(matches!(
+                err.kind(),
+                io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData
+            ));
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use std::fmt;
+
+/// Errors returned by the fallible methods of [`ChthollyTree`](crate::ChthollyTree)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChthollyError {
+    /// the range contains no element
+    EmptyRange,
+    /// the start of the range is greater than its end
+    ReversedRange { start: usize, end: usize },
+    /// the range or index goes past the end of the tree
+    ///
+    /// `index` is `usize::MAX` if the bound itself overflows
+    OutOfBounds { index: usize, len: usize },
+    /// an arithmetic operation overflowed under the [`Checked`](crate::arith::Checked) policy
+    Overflow,
+}
+
+impl fmt::Display for ChthollyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyRange => write!(f, "range is empty"),
+            Self::ReversedRange { start, end } => {
+                write!(f, "range starts at {} but ends at {}", start, end)
+            }
+            Self::OutOfBounds { index, len } => {
+                write!(f, "index {} out of range for tree of length {}", index, len)
+            }
+            Self::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+impl std::error::Error for ChthollyError {}
@@ -0,0 +1,163 @@
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+use std::ops::{Deref, Range, RangeBounds};
+
+use crate::ChthollyTree;
+
+/// A [`ChthollyTree`] with a reverse index from each value to the nodes holding it
+///
+/// `push`, `split`, `assign` and `map_range` are provided here so that the index follows
+/// them, the rest of the tree can be read through `Deref`.
+#[derive(Debug)]
+pub struct IndexedTree<T> {
+    tree: ChthollyTree<T>,
+    index: HashMap<T, BTreeSet<usize>>,
+}
+
+impl<T> IndexedTree<T> {
+    pub fn new() -> Self {
+        Self {
+            tree: ChthollyTree::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> ChthollyTree<T> {
+        self.tree
+    }
+}
+
+impl<T> Default for IndexedTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for IndexedTree<T> {
+    type Target = ChthollyTree<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tree
+    }
+}
+
+impl<T: Clone + Eq + Hash> IndexedTree<T> {
+    /// iterates over the bounds of the nodes holding `value`, in order
+    ///
+    /// this costs O(k log n) for k matching nodes instead of a scan of the whole tree
+    pub fn ranges_of<'a>(&'a self, value: &T) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.index
+            .get(value)
+            .into_iter()
+            .flatten()
+            .map(move |&start| start..self.tree.inner[&start].0)
+    }
+
+    pub fn push(&mut self, value: T) {
+        let at = self.tree.len();
+        self.tree.push(value);
+        if let Some((_, val)) = self.tree.inner.get(&at) {
+            self.index.entry(val.clone()).or_default().insert(at);
+        }
+    }
+
+    /// see [`ChthollyTree::split`]
+    pub fn split(&mut self, at: usize) {
+        self.tree.split(at);
+        if let Some((_, val)) = self.tree.inner.get(&at) {
+            self.index.entry(val.clone()).or_default().insert(at);
+        }
+    }
+
+    /// see [`ChthollyTree::assign`]
+    pub fn assign(&mut self, val: T, range: impl RangeBounds<usize>) {
+        self.update(range, |tree, rg| tree.assign(val, rg));
+    }
+
+    /// see [`ChthollyTree::map_range`]
+    pub fn map_range(&mut self, f: impl Fn(&mut T), range: impl RangeBounds<usize>) {
+        self.update(range, |tree, rg| tree.map_range(f, rg));
+    }
+
+    /// runs `f` on the resolved `range`, dropping the affected nodes from the index before
+    /// and adding them back after
+    fn update(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        f: impl FnOnce(&mut ChthollyTree<T>, Range<usize>),
+    ) {
+        let (rg, starts) = match self.tree.affected(range) {
+            Some(affected) => affected,
+            _ => return,
+        };
+
+        for (start, (_, val)) in self.tree.inner.range(starts.clone()) {
+            if let Some(nodes) = self.index.get_mut(val) {
+                nodes.remove(start);
+                if nodes.is_empty() {
+                    self.index.remove(val);
+                }
+            }
+        }
+        f(&mut self.tree, rg);
+        for (start, (_, val)) in self.tree.inner.range(starts) {
+            self.index.entry(val.clone()).or_default().insert(*start);
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> From<ChthollyTree<T>> for IndexedTree<T> {
+    fn from(tree: ChthollyTree<T>) -> Self {
+        let mut index = HashMap::<_, BTreeSet<_>>::new();
+        for (start, (_, val)) in tree.inner.iter() {
+            index.entry(val.clone()).or_default().insert(*start);
+        }
+        Self { tree, index }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IndexedTree;
+    use crate::ChthollyTree;
+
+    fn check(tree: &IndexedTree<i32>) {
+        for val in 0..5 {
+            assert_eq!(
+                tree.ranges_of(&val).collect::<Vec<_>>(),
+                tree.runs()
+                    .filter(|(_, v)| **v == val)
+                    .map(|(run, _)| run)
+                    .collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(
+            tree.index
+                .values()
+                .map(|starts| starts.len())
+                .sum::<usize>(),
+            tree.runs().count()
+        );
+    }
+
+    #[test]
+    fn ranges_of() {
+        let mut tree = IndexedTree::from(ChthollyTree::from_iter([0, 0, 1, 2, 2, 1]));
+        check(&tree);
+        assert_eq!(tree.ranges_of(&1).collect::<Vec<_>>(), [2..3, 5..6]);
+
+        tree.push(1);
+        tree.push(3);
+        check(&tree);
+        tree.assign(4, 1..3);
+        check(&tree);
+        tree.split(4);
+        check(&tree);
+        tree.map_range(|x| *x = (*x + 1) % 5, 3..7);
+        check(&tree);
+        tree.assign(0, ..);
+        check(&tree);
+        assert_eq!(tree.ranges_of(&0).next(), Some(0..8));
+        assert_eq!(tree.ranges_of(&4).next(), None);
+    }
+}
@@ -1,10 +1,42 @@
-use std::collections::BTreeMap;
-use std::iter::FromIterator;
-use std::ops::RangeBounds;
+use std::cmp::Ordering;
+use std::collections::{btree_map, BTreeMap};
+use std::hash::{Hash, Hasher};
+use std::iter::{self, FromIterator, FusedIterator};
+use std::mem;
+use std::ops::{Bound, Index, Range, RangeBounds, RangeInclusive};
 
-use num_traits::{Num, NumCast};
+use num_traits::{Num, NumCast, PrimInt, ToPrimitive, Zero};
 
-#[derive(Debug, Default)]
+use aggregate::RunAggregate;
+use arith::{ArithmeticPolicy, Checked, Saturating, Wrapping};
+
+pub mod aggregate;
+pub mod arith;
+mod cached;
+pub mod codec;
+mod error;
+mod indexed;
+mod modint;
+mod observer;
+#[cfg(feature = "serde")]
+mod serde_impl;
+
+pub use cached::{CachedTree, Monoid};
+pub use error::ChthollyError;
+pub use indexed::IndexedTree;
+pub use modint::ModInt;
+pub use observer::{ObservedTree, Observer};
+
+/// A sequence stored as runs of equal values, keyed by the start of each run.
+///
+/// Methods taking a `RangeBounds<usize>` resolve it the same way slice indexing does:
+/// they panic if the start is greater than the end or the end is greater than `len`,
+/// and do nothing on an empty range. Their `try_*` counterparts report all of these
+/// cases as a [`ChthollyError`] instead.
+///
+/// Comparisons and hashing look at the sequence of elements only, so trees holding the
+/// same elements are equal however their nodes are split.
+#[derive(Debug, Default, Clone)]
 pub struct ChthollyTree<T> {
     inner: BTreeMap<usize, (usize, T)>,
     len: usize,
@@ -18,6 +50,34 @@ impl<T> ChthollyTree<T> {
         }
     }
 
+    /// builds a tree with one node per `(count, value)` pair, skipping empty runs
+    ///
+    /// adjacent runs holding equal values are kept as separate nodes
+    ///
+    /// # Panic
+    ///
+    /// panic if the total length overflows `usize`
+    pub fn from_runs(runs: impl IntoIterator<Item = (usize, T)>) -> Self {
+        let mut inner = BTreeMap::new();
+        let mut len = 0usize;
+        for (count, val) in runs {
+            if count == 0 {
+                continue;
+            }
+            let end = len
+                .checked_add(count)
+                .expect("total length overflows usize");
+            inner.insert(len, (end, val));
+            len = end;
+        }
+        Self { inner, len }
+    }
+
+    /// a tree holding `n` copies of `value`, like `vec![value; n]`
+    pub fn from_elem(value: T, n: usize) -> Self {
+        Self::from_runs([(n, value)])
+    }
+
     pub const fn len(&self) -> usize {
         self.len
     }
@@ -26,10 +86,77 @@ impl<T> ChthollyTree<T> {
         self.len() == 0
     }
 
+    /// returns the element at position `at`, or `None` if `at` >= `len`
+    pub fn get(&self, at: usize) -> Option<&T> {
+        self.run_at(at).map(|(_, val)| val)
+    }
+
+    /// returns the bounds and the value of the node containing position `at`,
+    /// or `None` if `at` >= `len`
+    pub fn run_at(&self, at: usize) -> Option<(Range<usize>, &T)> {
+        if at >= self.len() {
+            return None;
+        }
+        let (&l, (r, val)) = self.inner.range(..=at).next_back()?;
+        Some((l..*r, val))
+    }
+
     pub fn iter(&self) -> Iter<'_, T> {
+        self.iter_range(..)
+    }
+
+    /// iterates over the elements in `range`
+    pub fn iter_range(&self, range: impl RangeBounds<usize>) -> Iter<'_, T> {
+        let (l, r) = self.bounds(range).unwrap_or((0, 0));
         Iter {
-            cur: None,
-            iter: self.inner.iter(),
+            runs: self.runs_between(l, r),
+            front: None,
+            back: None,
+            len: r - l,
+        }
+    }
+
+    /// returns the index of the first element satisfying `pred`
+    ///
+    /// `pred` is called once per node rather than once per element
+    pub fn position(&self, mut pred: impl FnMut(&T) -> bool) -> Option<usize> {
+        self.find_run(&mut pred).map(|(run, _)| run.start)
+    }
+
+    /// returns the index of the last element satisfying `pred`
+    ///
+    /// `pred` is called once per node rather than once per element
+    pub fn rposition(&self, mut pred: impl FnMut(&T) -> bool) -> Option<usize> {
+        self.runs()
+            .rev()
+            .find(|(_, val)| pred(val))
+            .map(|(run, _)| run.end - 1)
+    }
+
+    /// returns the bounds and the value of the first node satisfying `pred`
+    pub fn find_run(&self, mut pred: impl FnMut(&T) -> bool) -> Option<(Range<usize>, &T)> {
+        self.runs().find(|(_, val)| pred(val))
+    }
+
+    /// iterates over the nodes as `(bounds, value)` pairs
+    pub fn runs(&self) -> Runs<'_, T> {
+        self.runs_between(0, self.len())
+    }
+
+    /// iterates over the nodes as `(bounds, value)` pairs, allowing each value to be
+    /// modified
+    pub fn runs_mut(&mut self) -> RunsMut<'_, T> {
+        RunsMut {
+            iter: self.inner.iter_mut(),
+        }
+    }
+
+    /// iterates over the nodes overlapping `range` as `(bounds, value)` pairs, with the
+    /// bounds of the first and the last node clipped to the range
+    pub fn runs_in(&self, range: impl RangeBounds<usize>) -> Runs<'_, T> {
+        match self.bounds(range) {
+            Some((l, r)) => self.runs_between(l, r),
+            None => self.runs_between(0, 0),
         }
     }
 
@@ -42,35 +169,354 @@ impl<T> ChthollyTree<T> {
             .iter()
             .fold(init, |acc, (l, (r, val))| f(acc, r - l, val))
     }
+
+    pub fn fold_range<Acc>(
+        &self,
+        init: Acc,
+        f: impl Fn(Acc, usize, &T) -> Acc,
+        range: impl RangeBounds<usize>,
+    ) -> Acc {
+        let (l, r) = match self.bounds(range) {
+            Some(rg) => rg,
+            _ => return init,
+        };
+
+        self.runs_between(l, r)
+            .fold(init, |acc, (run, val)| f(acc, run.len(), val))
+    }
+
+    pub fn try_fold_range<Acc>(
+        &self,
+        init: Acc,
+        f: impl Fn(Acc, usize, &T) -> Acc,
+        range: impl RangeBounds<usize>,
+    ) -> Result<Acc, ChthollyError> {
+        let (l, r) = self.try_bounds(range)?;
+
+        Ok(self
+            .runs_between(l, r)
+            .fold(init, |acc, (run, val)| f(acc, run.len(), val)))
+    }
+
+    /// aggregates the elements in `range` with `A`, one node at a time
+    pub fn aggregate<A: RunAggregate<T>>(&self, range: impl RangeBounds<usize>) -> A::Output {
+        self.runs_in(range).fold(A::identity(), |acc, (run, val)| {
+            A::combine(acc, A::from_run(run.len(), val))
+        })
+    }
+
+    /// resolves `range` the same way slice indexing does, returning `None` for empty ranges
+    ///
+    /// # Panic
+    ///
+    /// panic if the start or the end is greater than `len`, or the start is greater than the end
+    fn bounds(&self, range: impl RangeBounds<usize>) -> Option<(usize, usize)> {
+        match self.try_bounds(range) {
+            Ok(rg) => Some(rg),
+            Err(ChthollyError::EmptyRange) => None,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// resolves `range` like [`bounds`](Self::bounds), along with the starts of the nodes
+    /// that an update of it may touch
+    ///
+    /// an update of `[l, r)` only splits the nodes at `l` and `r` and changes the nodes
+    /// between them, so every node it touches starts in `[start of the node containing l, r]`,
+    /// both before and after the update. The wrappers rebuild their state for these nodes.
+    pub(crate) fn affected(
+        &self,
+        range: impl RangeBounds<usize>,
+    ) -> Option<(Range<usize>, RangeInclusive<usize>)> {
+        let (l, r) = self.bounds(range)?;
+        let lo = self.run_at(l).unwrap().0.start;
+        Some((l..r, lo..=r))
+    }
+
+    fn try_bounds(&self, range: impl RangeBounds<usize>) -> Result<(usize, usize), ChthollyError> {
+        let out_of_bounds = ChthollyError::OutOfBounds {
+            index: usize::MAX,
+            len: self.len(),
+        };
+        let l = match range.start_bound() {
+            Bound::Included(&l) => l,
+            Bound::Excluded(&l) => l.checked_add(1).ok_or(out_of_bounds)?,
+            Bound::Unbounded => 0,
+        };
+        let r = match range.end_bound() {
+            Bound::Included(&r) => r.checked_add(1).ok_or(out_of_bounds)?,
+            Bound::Excluded(&r) => r,
+            Bound::Unbounded => self.len(),
+        };
+
+        if l > self.len() {
+            return Err(ChthollyError::OutOfBounds {
+                index: l,
+                len: self.len(),
+            });
+        }
+        if l > r {
+            return Err(ChthollyError::ReversedRange { start: l, end: r });
+        }
+        if r > self.len() {
+            return Err(ChthollyError::OutOfBounds {
+                index: r,
+                len: self.len(),
+            });
+        }
+        if l == r {
+            return Err(ChthollyError::EmptyRange);
+        }
+
+        Ok((l, r))
+    }
+
+    /// moves every node starting at or after `at` to `f(l)..f(r)`, `at` must be a node boundary
+    ///
+    /// this reinserts every node of the tail one at a time, so it costs O(k log n) for the
+    /// k nodes after `at`
+    fn shift_tail(&mut self, at: usize, f: impl Fn(usize) -> usize) {
+        let tail = self.inner.split_off(&at);
+        self.inner
+            .extend(tail.into_iter().map(|(l, (r, val))| (f(l), (f(r), val))));
+    }
+
+    /// iterates over the nodes overlapping `[l, r)`, with the first and the last one
+    /// clipped to the range
+    fn runs_between(&self, l: usize, r: usize) -> Runs<'_, T> {
+        let start = self
+            .inner
+            .range(..=l)
+            .next_back()
+            .map_or(l, |(&start, _)| start);
+        Runs {
+            iter: self.inner.range(start..r),
+            l,
+            r,
+        }
+    }
 }
 
 impl<T: Num + NumCast + Clone> ChthollyTree<T> {
+    /// sums all elements
+    ///
+    /// # Panic
+    ///
+    /// panic if a node length does not fit in `T`, and on overflow in debug builds, use
+    /// [`checked_sum`](Self::checked_sum) and friends to handle these cases
     pub fn sum(&self) -> T {
-        self.fold(T::zero(), |acc, repeat, val| {
-            acc + T::from(repeat).unwrap() * val.clone()
+        self.aggregate::<aggregate::Sum>(..)
+    }
+
+    /// sums the elements in `range`
+    ///
+    /// # Panic
+    ///
+    /// panic if a node length does not fit in `T`, and on overflow in debug builds, use
+    /// [`checked_range_sum`](Self::checked_range_sum) and friends to handle these cases
+    pub fn range_sum(&self, range: impl RangeBounds<usize>) -> T {
+        self.aggregate::<aggregate::Sum>(range)
+    }
+}
+
+impl<T: Zero> ChthollyTree<T> {
+    /// sums all elements, returning `None` if a partial sum overflows, see
+    /// [`checked_range_sum`](Self::checked_range_sum)
+    pub fn checked_sum(&self) -> Option<T>
+    where
+        Checked: ArithmeticPolicy<T>,
+    {
+        self.checked_range_sum(..)
+    }
+
+    /// sums all elements, wrapping around on overflow
+    pub fn wrapping_sum(&self) -> T
+    where
+        Wrapping: ArithmeticPolicy<T>,
+    {
+        self.wrapping_range_sum(..)
+    }
+
+    /// sums all elements, saturating at the numeric bounds on overflow, see
+    /// [`saturating_range_sum`](Self::saturating_range_sum)
+    pub fn saturating_sum(&self) -> T
+    where
+        Saturating: ArithmeticPolicy<T>,
+    {
+        self.saturating_range_sum(..)
+    }
+
+    /// sums the elements in `range`, returning `None` if a partial sum overflows
+    ///
+    /// this agrees with adding the elements one by one with `checked_add`, even where the
+    /// total of a single node does not fit in `T`
+    pub fn checked_range_sum(&self, range: impl RangeBounds<usize>) -> Option<T>
+    where
+        Checked: ArithmeticPolicy<T>,
+    {
+        self.sum_with::<Checked>(range)
+    }
+
+    /// sums the elements in `range`, wrapping around on overflow
+    pub fn wrapping_range_sum(&self, range: impl RangeBounds<usize>) -> T
+    where
+        Wrapping: ArithmeticPolicy<T>,
+    {
+        self.sum_with::<Wrapping>(range).unwrap()
+    }
+
+    /// sums the elements in `range`, saturating at the numeric bounds on overflow
+    ///
+    /// the total of each node saturates before it is added, so once the sum has
+    /// saturated the result may differ from saturating element by element
+    pub fn saturating_range_sum(&self, range: impl RangeBounds<usize>) -> T
+    where
+        Saturating: ArithmeticPolicy<T>,
+    {
+        self.sum_with::<Saturating>(range).unwrap()
+    }
+
+    fn sum_with<P: ArithmeticPolicy<T>>(&self, range: impl RangeBounds<usize>) -> Option<T> {
+        self.runs_in(range).try_fold(T::zero(), |acc, (run, val)| {
+            Self::add_run::<P>(acc, run.len(), val)
+        })
+    }
+
+    /// adds `n` copies of `val` to `acc`, failing only if adding them one by one would
+    ///
+    /// the running sum moves in one direction within a run, so this fails exactly when the
+    /// final sum overflows. The total of the run alone may overflow even if the final sum
+    /// does not, in which case the run is added in two halves.
+    fn add_run<P: ArithmeticPolicy<T>>(acc: T, n: usize, val: &T) -> Option<T> {
+        if let Some(sum) = P::repeat(n, val).and_then(|run| P::add(&acc, &run)) {
+            return Some(sum);
+        }
+        if n <= 1 {
+            return None;
+        }
+        let acc = Self::add_run::<P>(acc, n / 2, val)?;
+        Self::add_run::<P>(acc, n - n / 2, val)
+    }
+}
+
+impl<T: ToPrimitive> ChthollyTree<T> {
+    /// sums all elements in the accumulator type `Acc`, such as `i128` or `f64`
+    ///
+    /// # Panic
+    ///
+    /// panic if an element or a node length cannot be represented in `Acc`
+    pub fn sum_as<Acc: Num + NumCast>(&self) -> Acc
+    where
+        T: Clone,
+    {
+        self.fold(Acc::zero(), |acc, repeat, val| {
+            let repeat = Acc::from(repeat).expect("node length does not fit in the accumulator");
+            let val = Acc::from(val.clone()).expect("element does not fit in the accumulator");
+            acc + repeat * val
         })
     }
+}
 
-    pub fn range_sum(&mut self, range: impl RangeBounds<usize>) -> T {
+impl<T: PrimInt> ChthollyTree<T> {
+    /// computes the sum of `x ^ exp` over the integer elements `x` in `range`, modulo `modulus`
+    ///
+    /// negative elements are taken modulo `modulus` first
+    ///
+    /// # Panic
+    ///
+    /// panic if `modulus` is 0 or an element does not fit in `i128`
+    pub fn range_pow_sum(&self, range: impl RangeBounds<usize>, exp: u64, modulus: u64) -> u64 {
+        assert!(modulus != 0, "modulus must not be zero");
+        let m = modulus as u128;
         self.fold_range(
-            T::zero(),
-            |acc, repeat, val| acc + T::from(repeat).unwrap() * val.clone(),
+            0,
+            |acc, repeat, val| {
+                let base = val
+                    .to_i128()
+                    .expect("element does not fit in i128")
+                    .rem_euclid(modulus as i128) as u64;
+                let term = modint::pow_mod(base, exp, modulus) as u128 * (repeat as u128 % m) % m;
+                ((acc as u128 + term) % m) as u64
+            },
             range,
-        )
+        ) % modulus
     }
 }
 
 impl<T: Eq> ChthollyTree<T> {
     pub fn push(&mut self, value: T) {
+        self.push_observed(value, &mut ());
+    }
+
+    /// appends `count` copies of `value`, extending the last node if it holds an equal value
+    ///
+    /// # Panic
+    ///
+    /// panic if the total length overflows `usize`
+    pub fn extend_run(&mut self, count: usize, value: T) {
+        if count == 0 {
+            return;
+        }
+        let end = self
+            .len
+            .checked_add(count)
+            .expect("total length overflows usize");
         match self.inner.last_entry() {
             Some(mut entry) if entry.get().1 == value => {
-                entry.get_mut().0 += 1;
+                entry.get_mut().0 = end;
             }
             _ => {
-                self.inner.insert(self.len, (self.len + 1, value));
+                self.inner.insert(self.len, (end, value));
+            }
+        }
+        self.len = end;
+    }
+
+    /// moves all elements of `other` to the end of `self`, leaving `other` empty
+    ///
+    /// the first node of `other` is merged into the last node of `self` if they hold equal
+    /// values, the other nodes are kept as they are
+    ///
+    /// # Panic
+    ///
+    /// panic if the total length overflows `usize`
+    pub fn append(&mut self, other: &mut Self) {
+        let offset = self.len;
+        let len = offset
+            .checked_add(other.len)
+            .expect("total length overflows usize");
+        let mut nodes = mem::replace(other, Self::new()).inner.into_iter();
+        if let Some((_, (r, val))) = nodes.next() {
+            self.extend_run(r, val);
+        }
+        self.inner
+            .extend(nodes.map(|(l, (r, val))| (offset + l, (offset + r, val))));
+        self.len = len;
+    }
+
+    fn push_observed(&mut self, value: T, observer: &mut impl Observer<T>) {
+        observer.on_push(self.len, &value);
+        self.extend_run(1, value);
+    }
+
+    /// merges all adjacent nodes holding equal values in one pass
+    pub fn compact(&mut self) {
+        let mut inner = BTreeMap::new();
+        let mut last: Option<(usize, usize, T)> = None;
+        for (l, (r, val)) in std::mem::take(&mut self.inner) {
+            match last.as_mut() {
+                Some((_, lr, lval)) if *lval == val => *lr = r,
+                _ => {
+                    if let Some((ll, lr, lval)) = last.replace((l, r, val)) {
+                        inner.insert(ll, (lr, lval));
+                    }
+                }
             }
         }
-        self.len += 1;
+        if let Some((ll, lr, lval)) = last {
+            inner.insert(ll, (lr, lval));
+        }
+        self.inner = inner;
     }
 }
 
@@ -78,68 +524,154 @@ impl<T: Clone> ChthollyTree<T> {
     /// splits a node [l, r) into [l, at) and [at, r)
     /// # Panic
     ///
-    /// panic if `at` >= `len`
+    /// panic if `at` > `len`
     pub fn split(&mut self, at: usize) {
-        debug_assert!(at < self.len());
+        if let Err(e) = self.try_split(at) {
+            panic!("{}", e);
+        }
+    }
+
+    /// same as [`split`](Self::split), but returns an error instead of panicking
+    pub fn try_split(&mut self, at: usize) -> Result<(), ChthollyError> {
+        self.split_observed(at, &mut ())
+    }
+
+    fn split_observed(
+        &mut self,
+        at: usize,
+        observer: &mut impl Observer<T>,
+    ) -> Result<(), ChthollyError> {
+        if at > self.len() {
+            return Err(ChthollyError::OutOfBounds {
+                index: at,
+                len: self.len(),
+            });
+        }
         if at == 0 {
-            return;
+            return Ok(());
         }
 
-        let (_, (r, val)) = self.inner.range_mut(..=at).next_back().unwrap();
+        let (&l, (r, val)) = self.inner.range_mut(..=at).next_back().unwrap();
         let rb = *r;
 
-        if at != rb {
+        if at != l && at != rb {
             let value = val.clone();
             *r = at;
             self.inner.insert(at, (rb, value));
+            observer.on_split(at);
         }
+        Ok(())
     }
 
-    fn split_range(&mut self, range: impl RangeBounds<usize>) -> Option<(usize, usize)> {
-        let l = match range.start_bound() {
-            std::ops::Bound::Included(&l) => l,
-            std::ops::Bound::Excluded(l) => l + 1,
-            std::ops::Bound::Unbounded => 0,
-        };
-        let r = match range.end_bound() {
-            std::ops::Bound::Included(r) => match r.checked_sub(1) {
-                Some(r) => r,
-                None => return None,
-            },
-            std::ops::Bound::Excluded(&r) => r,
-            std::ops::Bound::Unbounded => self.len(),
-        };
-
-        if l >= r || r > self.len() {
+    /// returns a mutable reference to the element at position `at`, or `None` if
+    /// `at` >= `len`
+    ///
+    /// the element is split out into a node of its own, so that mutating it leaves
+    /// its neighbours untouched
+    pub fn get_mut(&mut self, at: usize) -> Option<&mut T> {
+        if at >= self.len() {
             return None;
         }
+        self.split(at);
+        self.split(at + 1);
+        self.inner.get_mut(&at).map(|(_, val)| val)
+    }
+
+    fn split_range(&mut self, range: impl RangeBounds<usize>) -> Option<(usize, usize)> {
+        self.split_range_observed(range, &mut ())
+    }
+
+    fn split_range_observed(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        observer: &mut impl Observer<T>,
+    ) -> Option<(usize, usize)> {
+        let (l, r) = self.bounds(range)?;
+
+        self.split_observed(l, observer).unwrap();
+        self.split_observed(r, observer).unwrap();
+
+        Some((l, r))
+    }
+
+    fn try_split_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+    ) -> Result<(usize, usize), ChthollyError> {
+        let (l, r) = self.try_bounds(range)?;
 
         self.split(l);
         self.split(r);
 
-        Some((l, r))
+        Ok((l, r))
     }
 
     pub fn assign(&mut self, val: T, range: impl RangeBounds<usize>) {
-        let (l, r) = match self.split_range(range) {
+        self.assign_observed(val, range, &mut ());
+    }
+
+    /// same as [`assign`](Self::assign), but passes every overwritten node to `f` as
+    /// `(bounds, old value)`
+    pub fn assign_with(
+        &mut self,
+        val: T,
+        range: impl RangeBounds<usize>,
+        mut f: impl FnMut(Range<usize>, T),
+    ) {
+        self.assign_observed(val, range, &mut f);
+    }
+
+    fn assign_observed(
+        &mut self,
+        val: T,
+        range: impl RangeBounds<usize>,
+        observer: &mut impl Observer<T>,
+    ) {
+        let (l, r) = match self.split_range_observed(range, observer) {
             Some(rg) => rg,
             _ => return,
         };
 
+        self.assign_split(val, l, r, observer);
+    }
+
+    pub fn try_assign(
+        &mut self,
+        val: T,
+        range: impl RangeBounds<usize>,
+    ) -> Result<(), ChthollyError> {
+        let (l, r) = self.try_split_range(range)?;
+
+        self.assign_split(val, l, r, &mut ());
+        Ok(())
+    }
+
+    /// overwrites `[l, r)`, which must already be split at both ends
+    fn assign_split(&mut self, val: T, l: usize, r: usize, observer: &mut impl Observer<T>) {
         self.inner
-            .range(l + 1..r)
+            .range(l..r)
             .map(|(k, _)| *k)
             .collect::<Vec<_>>()
             .iter()
             .for_each(|k| {
-                self.inner.remove(k);
+                let (nr, old) = self.inner.remove(k).unwrap();
+                observer.on_overwrite(*k..nr, old);
             });
 
         self.inner.insert(l, (r, val));
     }
 
     pub fn map_range(&mut self, f: impl Fn(&mut T), range: impl RangeBounds<usize>) {
-        let (l, r) = match self.split_range(range) {
+        self.map_range_observed(f, range, &mut ());
+    }
+
+    fn map_range_observed(
+        &mut self,
+        f: impl Fn(&mut T),
+        range: impl RangeBounds<usize>,
+        observer: &mut impl Observer<T>,
+    ) {
+        let (l, r) = match self.split_range_observed(range, observer) {
             Some(rg) => rg,
             _ => return,
         };
@@ -147,56 +679,731 @@ impl<T: Clone> ChthollyTree<T> {
         self.inner.range_mut(l..r).for_each(|(_, (_, val))| f(val));
     }
 
-    pub fn fold_range<Acc>(
+    pub fn try_map_range(
         &mut self,
-        init: Acc,
-        f: impl Fn(Acc, usize, &T) -> Acc,
+        f: impl Fn(&mut T),
         range: impl RangeBounds<usize>,
-    ) -> Acc {
-        let (l, r) = match self.split_range(range) {
-            Some(rg) => rg,
-            _ => return init,
-        };
+    ) -> Result<(), ChthollyError> {
+        let (l, r) = self.try_split_range(range)?;
 
-        self.inner
-            .range(l..r)
-            .fold(init, |acc, (l, (r, val))| f(acc, r - l, val))
+        self.inner.range_mut(l..r).for_each(|(_, (_, val))| f(val));
+        Ok(())
     }
 }
 
-impl<T: Eq> FromIterator<T> for ChthollyTree<T> {
-    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
-        let mut tree = Self::new();
-        for i in iter {
-            tree.push(i);
+impl<T: Clone + Eq> ChthollyTree<T> {
+    /// merges the node starting at `at` into the previous one if their values are equal
+    fn merge_at(&mut self, at: usize) {
+        self.merge_at_observed(at, &mut ());
+    }
+
+    fn merge_at_observed(&mut self, at: usize, observer: &mut impl Observer<T>) {
+        if at == 0 {
+            return;
+        }
+        let (r, val) = match self.inner.get(&at) {
+            Some(node) => node,
+            None => return,
+        };
+        let r = *r;
+        let (&pl, (_, prev)) = self.inner.range(..at).next_back().unwrap();
+        if prev == val {
+            self.inner.remove(&at);
+            self.inner.get_mut(&pl).unwrap().0 = r;
+            observer.on_merge(at);
         }
-        tree
     }
-}
 
-pub struct Iter<'a, T> {
-    cur: Option<(usize, &'a T)>,
-    iter: std::collections::btree_map::Iter<'a, usize, (usize, T)>,
-}
+    /// merges every node boundary in `[l, r]` whose neighbours hold equal values
+    fn merge_range(&mut self, l: usize, r: usize) {
+        self.merge_range_observed(l, r, &mut ());
+    }
 
-impl<'a, T> Iterator for Iter<'a, T> {
-    type Item = &'a T;
+    fn merge_range_observed(&mut self, l: usize, r: usize, observer: &mut impl Observer<T>) {
+        self.inner
+            .range(l.max(1)..=r)
+            .map(|(k, _)| *k)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .for_each(|k| self.merge_at_observed(k, observer));
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let cur = match self.cur.as_mut() {
-            None | Some((0, _)) => self
-                .cur
-                .insert(self.iter.next().map(|(l, (r, val))| (r - l, val))?),
-            Some(cur) => cur,
+    /// same as [`assign`](Self::assign), but merges the written node with
+    /// its neighbours if they hold an equal value
+    pub fn assign_merge(&mut self, val: T, range: impl RangeBounds<usize>) {
+        self.assign_merge_observed(val, range, &mut ());
+    }
+
+    fn assign_merge_observed(
+        &mut self,
+        val: T,
+        range: impl RangeBounds<usize>,
+        observer: &mut impl Observer<T>,
+    ) {
+        let (l, r) = match self.split_range_observed(range, observer) {
+            Some(rg) => rg,
+            _ => return,
         };
-        cur.0 -= 1;
-        Some(cur.1)
+
+        self.assign_split(val, l, r, observer);
+        self.merge_at_observed(r, observer);
+        self.merge_at_observed(l, observer);
     }
-}
 
-#[cfg(test)]
-mod test {
-    use crate::ChthollyTree;
+    /// same as [`map_range`](Self::map_range), but merges adjacent nodes
+    /// in and around the range that end up holding equal values
+    pub fn map_range_merge(&mut self, f: impl Fn(&mut T), range: impl RangeBounds<usize>) {
+        self.map_range_merge_observed(f, range, &mut ());
+    }
+
+    fn map_range_merge_observed(
+        &mut self,
+        f: impl Fn(&mut T),
+        range: impl RangeBounds<usize>,
+        observer: &mut impl Observer<T>,
+    ) {
+        let (l, r) = match self.split_range_observed(range, observer) {
+            Some(rg) => rg,
+            _ => return,
+        };
+
+        self.inner.range_mut(l..r).for_each(|(_, (_, val))| f(val));
+        self.merge_range_observed(l, r, observer);
+    }
+
+    /// inserts `value` at position `at`, shifting all elements after it to the right
+    ///
+    /// the keys of the nodes after `at` are shifted one by one, so this costs O(k log n)
+    /// for k such nodes, see [`insert_run`](Self::insert_run)
+    ///
+    /// # Panic
+    ///
+    /// panic if `at` > `len`
+    pub fn insert(&mut self, at: usize, value: T) {
+        self.insert_run(at, 1, value);
+    }
+
+    /// inserts `count` copies of `value` at position `at`, shifting all elements after it
+    /// to the right
+    ///
+    /// the cost does not depend on `count`, but the keys of the k nodes after `at` are
+    /// shifted one by one, which costs O(k log n), so edits near the start of a fragmented
+    /// tree are much slower than edits near its end
+    ///
+    /// # Panic
+    ///
+    /// panic if `at` > `len` or the total length overflows `usize`
+    pub fn insert_run(&mut self, at: usize, count: usize, value: T) {
+        assert!(
+            at <= self.len(),
+            "insertion index (is {}) should be <= len (is {})",
+            at,
+            self.len()
+        );
+        if count == 0 {
+            return;
+        }
+        let len = self
+            .len
+            .checked_add(count)
+            .expect("total length overflows usize");
+
+        self.split(at);
+        self.shift_tail(at, |k| k + count);
+        self.inner.insert(at, (at + count, value));
+        self.len = len;
+
+        self.merge_at(at + count);
+        self.merge_at(at);
+    }
+
+    /// removes and returns the element at position `at`, shifting all elements after it
+    /// to the left
+    ///
+    /// like [`insert_run`](Self::insert_run), this costs O(k log n) for the k nodes after `at`
+    ///
+    /// # Panic
+    ///
+    /// panic if `at` >= `len`
+    pub fn remove(&mut self, at: usize) -> T {
+        assert!(
+            at < self.len(),
+            "removal index (is {}) should be < len (is {})",
+            at,
+            self.len()
+        );
+
+        self.split(at);
+        self.split(at + 1);
+        let (_, val) = self.inner.remove(&at).unwrap();
+        self.shift_tail(at + 1, |k| k - 1);
+        self.len -= 1;
+
+        self.merge_at(at);
+        val
+    }
+
+    /// removes the elements in `range` and returns them as a new tree, shifting all
+    /// elements after the range to the left
+    ///
+    /// this costs O(k log n) for the k nodes in and after the range
+    ///
+    /// # Panic
+    ///
+    /// panic if the start or the end is greater than `len`, or the start is greater than the end
+    pub fn drain(&mut self, range: impl RangeBounds<usize>) -> Self {
+        let (l, r) = match self.split_range(range) {
+            Some(rg) => rg,
+            _ => return Self::new(),
+        };
+
+        let mut drained = self.inner.split_off(&l);
+        self.inner.append(&mut drained.split_off(&r));
+        self.shift_tail(r, |k| k - (r - l));
+        self.len -= r - l;
+        self.merge_at(l);
+
+        Self {
+            inner: drained
+                .into_iter()
+                .map(|(nl, (nr, val))| (nl - l, (nr - l, val)))
+                .collect(),
+            len: r - l,
+        }
+    }
+
+    /// adds `delta` to every element in `range`, handling overflow according to `policy`
+    ///
+    /// on overflow, returns [`ChthollyError::Overflow`] and leaves the tree unchanged
+    ///
+    /// a reversed or out of bounds range returns the same error as the `try_*` methods,
+    /// but an empty range does nothing and returns `Ok(())`
+    pub fn range_add<P: ArithmeticPolicy<T>>(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        delta: T,
+        _policy: P,
+    ) -> Result<(), ChthollyError> {
+        self.range_update(range, |val| P::add(val, &delta))
+    }
+
+    /// multiplies every element in `range` by `factor`, handling overflow according
+    /// to `policy`
+    ///
+    /// errors are reported as by [`range_add`](Self::range_add)
+    pub fn range_mul<P: ArithmeticPolicy<T>>(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        factor: T,
+        _policy: P,
+    ) -> Result<(), ChthollyError> {
+        self.range_update(range, |val| P::mul(val, &factor))
+    }
+
+    /// replaces every element `x` in `range` with `a * x + b`, handling overflow
+    /// according to `policy`
+    ///
+    /// errors are reported as by [`range_add`](Self::range_add)
+    pub fn range_affine<P: ArithmeticPolicy<T>>(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        a: T,
+        b: T,
+        _policy: P,
+    ) -> Result<(), ChthollyError> {
+        self.range_update(range, |val| P::add(&P::mul(&a, val)?, &b))
+    }
+
+    /// computes the new value of every node in `range` before touching the tree, so that
+    /// nothing changes if `f` fails on any of them
+    fn range_update(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        f: impl Fn(&T) -> Option<T>,
+    ) -> Result<(), ChthollyError> {
+        let (l, r) = match self.try_bounds(range) {
+            Ok(rg) => rg,
+            Err(ChthollyError::EmptyRange) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let values = self
+            .runs_between(l, r)
+            .map(|(_, val)| f(val))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(ChthollyError::Overflow)?;
+
+        self.split(l);
+        self.split(r);
+        self.inner
+            .range_mut(l..r)
+            .zip(values)
+            .for_each(|((_, (_, val)), new)| *val = new);
+        self.merge_range(l, r);
+        Ok(())
+    }
+}
+
+impl<T: PartialEq> ChthollyTree<T> {
+    /// counts the elements equal to `value`
+    pub fn count(&self, value: &T) -> usize {
+        self.count_in(.., value)
+    }
+
+    /// counts the elements in `range` equal to `value`
+    pub fn count_in(&self, range: impl RangeBounds<usize>, value: &T) -> usize {
+        self.runs_in(range)
+            .filter(|(_, val)| *val == value)
+            .map(|(run, _)| run.len())
+            .sum()
+    }
+}
+
+impl<T: Ord> ChthollyTree<T> {
+    /// returns the `k`-th smallest element in `range`, counting from 0, or `None` if
+    /// the range has no more than `k` elements
+    pub fn kth_smallest(&self, range: impl RangeBounds<usize>, k: usize) -> Option<&T> {
+        let mut runs = self.runs_in(range).collect::<Vec<_>>();
+        runs.sort_unstable_by_key(|(_, val)| *val);
+        Self::kth(runs, k)
+    }
+
+    /// returns the `k`-th largest element in `range`, counting from 0, or `None` if
+    /// the range has no more than `k` elements
+    pub fn kth_largest(&self, range: impl RangeBounds<usize>, k: usize) -> Option<&T> {
+        let mut runs = self.runs_in(range).collect::<Vec<_>>();
+        runs.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+        Self::kth(runs, k)
+    }
+
+    /// returns the smallest element in `range`, or `None` if the range is empty
+    pub fn range_min(&self, range: impl RangeBounds<usize>) -> Option<&T> {
+        self.extreme(range, |a, b| a < b).map(|(_, val)| val)
+    }
+
+    /// returns the largest element in `range`, or `None` if the range is empty
+    pub fn range_max(&self, range: impl RangeBounds<usize>) -> Option<&T> {
+        self.extreme(range, |a, b| a > b).map(|(_, val)| val)
+    }
+
+    /// returns the first index of the smallest element in `range`, or `None` if the range
+    /// is empty
+    pub fn range_argmin(&self, range: impl RangeBounds<usize>) -> Option<usize> {
+        self.extreme(range, |a, b| a < b).map(|(at, _)| at)
+    }
+
+    /// returns the first index of the largest element in `range`, or `None` if the range
+    /// is empty
+    pub fn range_argmax(&self, range: impl RangeBounds<usize>) -> Option<usize> {
+        self.extreme(range, |a, b| a > b).map(|(at, _)| at)
+    }
+
+    /// finds the first node in `range` whose value no later node is `better` than
+    fn extreme(
+        &self,
+        range: impl RangeBounds<usize>,
+        better: impl Fn(&T, &T) -> bool,
+    ) -> Option<(usize, &T)> {
+        self.runs_in(range)
+            .map(|(run, val)| (run.start, val))
+            .reduce(|best, cur| if better(cur.1, best.1) { cur } else { best })
+    }
+
+    fn kth(runs: Vec<(Range<usize>, &T)>, mut k: usize) -> Option<&T> {
+        for (run, val) in runs {
+            if k < run.len() {
+                return Some(val);
+            }
+            k -= run.len();
+        }
+        None
+    }
+}
+
+impl<T: Eq> FromIterator<T> for ChthollyTree<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T: Eq> Extend<T> for ChthollyTree<T> {
+    /// groups equal consecutive values before touching the nodes
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut values = iter.into_iter();
+        let mut val = match values.next() {
+            Some(val) => val,
+            None => return,
+        };
+        let mut count = 1;
+        for next in values {
+            if next == val {
+                count += 1;
+            } else {
+                self.extend_run(count, mem::replace(&mut val, next));
+                count = 1;
+            }
+        }
+        self.extend_run(count, val);
+    }
+}
+
+impl<T: Eq> From<Vec<T>> for ChthollyTree<T> {
+    fn from(vec: Vec<T>) -> Self {
+        Self::from_iter(vec)
+    }
+}
+
+impl<T: Eq + Clone> From<&[T]> for ChthollyTree<T> {
+    fn from(slice: &[T]) -> Self {
+        Self::from_runs(
+            slice
+                .chunk_by(|a, b| a == b)
+                .map(|run| (run.len(), run[0].clone())),
+        )
+    }
+}
+
+impl<T> Index<usize> for ChthollyTree<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match self.get(index) {
+            Some(val) => val,
+            None => panic!(
+                "index out of bounds: the len is {} but the index is {}",
+                self.len(),
+                index
+            ),
+        }
+    }
+}
+
+impl<T> ChthollyTree<T> {
+    /// pairs up the values of the overlapping parts of the nodes of `self` and `other`,
+    /// up to the shorter of the two
+    fn segments<'a, U>(
+        &'a self,
+        other: &'a ChthollyTree<U>,
+    ) -> impl Iterator<Item = (&'a T, &'a U)> {
+        let (mut a, mut b) = (self.inner.values(), other.inner.values());
+        let (mut x, mut y) = (a.next(), b.next());
+        iter::from_fn(move || {
+            let ((ra, va), (rb, vb)) = (x?, y?);
+            if ra <= rb {
+                x = a.next();
+            }
+            if rb <= ra {
+                y = b.next();
+            }
+            Some((va, vb))
+        })
+    }
+}
+
+impl<T: PartialEq> PartialEq for ChthollyTree<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.segments(other).all(|(a, b)| a == b)
+    }
+}
+
+impl<T: Eq> Eq for ChthollyTree<T> {}
+
+impl<T: PartialOrd> PartialOrd for ChthollyTree<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        for (a, b) in self.segments(other) {
+            match a.partial_cmp(b) {
+                Some(Ordering::Equal) => {}
+                ord => return ord,
+            }
+        }
+        Some(self.len.cmp(&other.len))
+    }
+}
+
+impl<T: Ord> Ord for ChthollyTree<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        for (a, b) in self.segments(other) {
+            match a.cmp(b) {
+                Ordering::Equal => {}
+                ord => return ord,
+            }
+        }
+        self.len.cmp(&other.len)
+    }
+}
+
+/// hashes the maximal runs of equal values, so that it agrees with `PartialEq`
+impl<T: Hash + PartialEq> Hash for ChthollyTree<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut runs = self.runs();
+        if let Some((first, mut val)) = runs.next() {
+            let mut count = first.len();
+            for (run, next) in runs {
+                if *next != *val {
+                    count.hash(state);
+                    val.hash(state);
+                    count = 0;
+                    val = next;
+                }
+                count += run.len();
+            }
+            count.hash(state);
+            val.hash(state);
+        }
+        self.len.hash(state);
+    }
+}
+
+pub struct Iter<'a, T> {
+    runs: Runs<'a, T>,
+    front: Option<(Range<usize>, &'a T)>,
+    back: Option<(Range<usize>, &'a T)>,
+    len: usize,
+}
+
+impl<'a, T> Iter<'a, T> {
+    /// makes sure `front` has an element left, or returns `None` if there are none
+    fn fill_front(&mut self) -> Option<&mut (Range<usize>, &'a T)> {
+        while self.front.as_ref().is_none_or(|(run, _)| run.is_empty()) {
+            self.front = Some(self.runs.next().or_else(|| self.back.take())?);
+        }
+        self.front.as_mut()
+    }
+
+    /// makes sure `back` has an element left, or returns `None` if there are none
+    fn fill_back(&mut self) -> Option<&mut (Range<usize>, &'a T)> {
+        while self.back.as_ref().is_none_or(|(run, _)| run.is_empty()) {
+            self.back = Some(self.runs.next_back().or_else(|| self.front.take())?);
+        }
+        self.back.as_mut()
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.nth(0)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+
+    /// skips whole nodes at a time, so it costs O(nodes skipped)
+    fn nth(&mut self, mut n: usize) -> Option<Self::Item> {
+        if n >= self.len {
+            self.len = 0;
+            return None;
+        }
+        self.len -= n + 1;
+        loop {
+            let (run, val) = self.fill_front()?;
+            if n < run.len() {
+                run.start += n + 1;
+                return Some(*val);
+            }
+            n -= run.len();
+            run.start = run.end;
+        }
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.nth_back(0)
+    }
+
+    /// skips whole nodes at a time, so it costs O(nodes skipped)
+    fn nth_back(&mut self, mut n: usize) -> Option<Self::Item> {
+        if n >= self.len {
+            self.len = 0;
+            return None;
+        }
+        self.len -= n + 1;
+        loop {
+            let (run, val) = self.fill_back()?;
+            if n < run.len() {
+                run.end -= n + 1;
+                return Some(*val);
+            }
+            n -= run.len();
+            run.end = run.start;
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<T: Clone> IntoIterator for ChthollyTree<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            len: self.len,
+            runs: self.inner.into_iter(),
+            front: None,
+            back: None,
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ChthollyTree<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut ChthollyTree<T> {
+    type Item = (Range<usize>, &'a mut T);
+    type IntoIter = RunsMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.runs_mut()
+    }
+}
+
+/// An owning iterator over the elements of a tree
+///
+/// a value is only cloned while its node still has more than one element left
+pub struct IntoIter<T> {
+    runs: btree_map::IntoIter<usize, (usize, T)>,
+    /// remaining count and value of the node being consumed from the front, the count is never 0
+    front: Option<(usize, T)>,
+    /// same as `front`, but for the back
+    back: Option<(usize, T)>,
+    len: usize,
+}
+
+impl<T: Clone> IntoIter<T> {
+    fn take_one(cur: &mut Option<(usize, T)>) -> Option<T> {
+        let (count, val) = cur.as_mut()?;
+        *count -= 1;
+        if *count == 0 {
+            cur.take().map(|(_, val)| val)
+        } else {
+            Some(val.clone())
+        }
+    }
+}
+
+impl<T: Clone> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        if self.front.is_none() {
+            self.front = self
+                .runs
+                .next()
+                .map(|(l, (r, val))| (r - l, val))
+                .or_else(|| self.back.take());
+        }
+        Self::take_one(&mut self.front)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        if self.back.is_none() {
+            self.back = self
+                .runs
+                .next_back()
+                .map(|(l, (r, val))| (r - l, val))
+                .or_else(|| self.front.take());
+        }
+        Self::take_one(&mut self.back)
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for IntoIter<T> {}
+
+impl<T: Clone> FusedIterator for IntoIter<T> {}
+
+pub struct Runs<'a, T> {
+    iter: btree_map::Range<'a, usize, (usize, T)>,
+    l: usize,
+    r: usize,
+}
+
+impl<'a, T> Iterator for Runs<'a, T> {
+    type Item = (Range<usize>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next()
+            .map(|(&l, (r, val))| (l.max(self.l)..(*r).min(self.r), val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Runs<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next_back()
+            .map(|(&l, (r, val))| (l.max(self.l)..(*r).min(self.r), val))
+    }
+}
+
+impl<T> FusedIterator for Runs<'_, T> {}
+
+pub struct RunsMut<'a, T> {
+    iter: btree_map::IterMut<'a, usize, (usize, T)>,
+}
+
+impl<'a, T> Iterator for RunsMut<'a, T> {
+    type Item = (Range<usize>, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(&l, (r, val))| (l..*r, val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for RunsMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(&l, (r, val))| (l..*r, val))
+    }
+}
+
+impl<T> ExactSizeIterator for RunsMut<'_, T> {}
+
+impl<T> FusedIterator for RunsMut<'_, T> {}
+
+#[cfg(test)]
+mod test {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::ops::Bound;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use crate::arith::{Checked, Saturating, Wrapping};
+    use crate::{ChthollyError, ChthollyTree, ModInt};
 
     #[test]
     fn from_iter() {
@@ -215,6 +1422,72 @@ mod test {
         );
     }
 
+    #[test]
+    fn from_runs() {
+        let tree = ChthollyTree::from_runs([(2, 'a'), (0, 'b'), (1, 'a'), (3, 'c')]);
+        check_nodes(&tree);
+        assert_eq!(tree.runs().count(), 3);
+        assert!(tree.iter().eq(&['a', 'a', 'a', 'c', 'c', 'c']));
+
+        let tree = ChthollyTree::from_elem(7, 4);
+        assert_eq!(tree.runs().collect::<Vec<_>>(), [(0..4, &7)]);
+        assert!(ChthollyTree::from_elem(7, 0).is_empty());
+
+        let mut rng = Rng(0xda942042e4dd58b5);
+        for _ in 0..200 {
+            let data = rng.data();
+            let expected = ChthollyTree::from_iter(data.iter().copied());
+            for tree in [
+                ChthollyTree::from(data.clone()),
+                ChthollyTree::from(data.as_slice()),
+            ] {
+                check_nodes(&tree);
+                assert!(tree.runs().eq(expected.runs()));
+            }
+        }
+    }
+
+    #[test]
+    fn extend_append() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2]);
+        tree.extend([2, 2, 3]);
+        tree.extend_run(0, 4);
+        tree.extend_run(2, 3);
+        assert_eq!(
+            tree.runs().collect::<Vec<_>>(),
+            [(0..2, &1), (2..5, &2), (5..8, &3)]
+        );
+
+        let mut other = ChthollyTree::from_iter([3, 5, 5]);
+        other.split(2);
+        tree.append(&mut other);
+        check_nodes(&tree);
+        assert!(other.is_empty());
+        assert_eq!(
+            tree.runs().collect::<Vec<_>>(),
+            [
+                (0..2, &1),
+                (2..5, &2),
+                (5..9, &3),
+                (9..10, &5),
+                (10..11, &5)
+            ]
+        );
+        tree.append(&mut ChthollyTree::new());
+        assert_eq!(tree.len(), 11);
+
+        let mut rng = Rng(0x5851f42d4c957f2d);
+        for _ in 0..200 {
+            let (mut a, mut b) = (rng.data(), rng.data());
+            let mut x = ChthollyTree::from_iter(a.iter().copied());
+            let mut y = ChthollyTree::from_iter(b.iter().copied());
+            x.append(&mut y);
+            a.append(&mut b);
+            check_nodes(&x);
+            assert_eq!(x, ChthollyTree::from(a));
+        }
+    }
+
     #[test]
     fn iter() {
         let data = [-1, 2, 2, 3, 0, 0, 0, -4, -4, 10, 10, 12];
@@ -252,6 +1525,65 @@ mod test {
         );
     }
 
+    #[test]
+    fn assign_merge() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 4, 4, 4, 5, 7, 8]);
+        tree.assign_merge(4, 2..4);
+        assert_eq!(
+            tree.inner
+                .iter()
+                .map(|(l, (r, v))| (*l, *r, *v))
+                .collect::<Vec<_>>(),
+            [(0, 2, 1), (2, 7, 4), (7, 8, 5), (8, 9, 7), (9, 10, 8)]
+        );
+        tree.assign_merge(1, 2..7);
+        assert_eq!(
+            tree.inner
+                .iter()
+                .map(|(l, (r, v))| (*l, *r, *v))
+                .collect::<Vec<_>>(),
+            [(0, 7, 1), (7, 8, 5), (8, 9, 7), (9, 10, 8)]
+        );
+    }
+
+    #[test]
+    fn map_range_merge() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 4, 4, 4, 5, 7, 8]);
+        tree.map_range_merge(|x| *x = (*x).max(4), 2..8);
+        assert_eq!(
+            tree.iter().copied().collect::<Vec<_>>(),
+            [1, 1, 4, 4, 4, 4, 4, 5, 7, 8]
+        );
+        assert_eq!(tree.inner.len(), 5);
+    }
+
+    #[test]
+    fn compact() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 4, 4, 4, 5, 7, 8]);
+        tree.split(1);
+        tree.split(5);
+        tree.assign(4, 3..4);
+        tree.compact();
+        assert_eq!(
+            tree.inner.into_iter().collect::<Vec<_>>(),
+            vec![
+                (0, (2, 1)),
+                (2, (3, 2)),
+                (3, (7, 4)),
+                (7, (8, 5)),
+                (8, (9, 7)),
+                (9, (10, 8))
+            ]
+        );
+
+        #[derive(PartialEq, Eq)]
+        struct NoClone(i32);
+        let mut tree = ChthollyTree::from_runs([(2, NoClone(1)), (1, NoClone(1)), (1, NoClone(2))]);
+        tree.compact();
+        check_nodes(&tree);
+        assert_eq!(tree.inner.len(), 2);
+    }
+
     #[test]
     fn sum() {
         let tree = ChthollyTree::from_iter([1, 1, 2, 3, 4, 4, 4, 5, 7, 8]);
@@ -260,10 +1592,561 @@ mod test {
 
     #[test]
     fn range_fold() {
-        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 4, 4, 4, 5, 7, 8]);
+        let tree = ChthollyTree::from_iter([1, 1, 2, 3, 4, 4, 4, 5, 7, 8]);
         assert_eq!(
             tree.range_sum(3..6),
-            [1, 1, 2, 3, 4, 4, 4, 5, 7, 8][3..6].into_iter().sum()
+            [1, 1, 2, 3, 4, 4, 4, 5, 7, 8][3..6].iter().sum()
+        );
+        assert_eq!(
+            tree.range_sum(1..8),
+            [1, 1, 2, 3, 4, 4, 4, 5, 7, 8][1..8].iter().sum()
+        );
+        assert_eq!(tree.fold_range(0, |acc, repeat, _| acc + repeat, 5..6), 1);
+        assert_eq!(tree.inner.len(), 7);
+    }
+
+    /// xorshift64, so that the randomised tests are reproducible
+    pub(crate) struct Rng(pub(crate) u64);
+
+    impl Rng {
+        pub(crate) fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        pub(crate) fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        pub(crate) fn data(&mut self) -> Vec<i64> {
+            let len = self.below(24);
+            (0..len).map(|_| self.below(4) as i64).collect()
+        }
+
+        /// arbitrary bounds, valid or not
+        pub(crate) fn any_bounds(&mut self, len: usize) -> (Bound<usize>, Bound<usize>) {
+            let bound = |rng: &mut Self| {
+                let at = match rng.below(11) {
+                    0 => usize::MAX,
+                    _ => rng.below(len + 3),
+                };
+                match rng.below(3) {
+                    0 => Bound::Included(at),
+                    1 => Bound::Excluded(at),
+                    _ => Bound::Unbounded,
+                }
+            };
+            (bound(self), bound(self))
+        }
+
+        /// bounds describing some `l..r` with `l <= r <= len`
+        pub(crate) fn valid_bounds(&mut self, len: usize) -> (Bound<usize>, Bound<usize>) {
+            let r = self.below(len + 1);
+            let l = self.below(r + 1);
+            let start = match self.below(3) {
+                0 if l == 0 => Bound::Unbounded,
+                1 if l > 0 => Bound::Excluded(l - 1),
+                _ => Bound::Included(l),
+            };
+            let end = match self.below(3) {
+                0 if r == len => Bound::Unbounded,
+                1 if r > 0 => Bound::Included(r - 1),
+                _ => Bound::Excluded(r),
+            };
+            (start, end)
+        }
+    }
+
+    fn check_nodes<T>(tree: &ChthollyTree<T>) {
+        let mut at = 0;
+        for (l, (r, _)) in tree.inner.iter() {
+            assert_eq!(*l, at);
+            assert!(l < r);
+            at = *r;
+        }
+        assert_eq!(at, tree.len());
+    }
+
+    #[test]
+    fn range_bounds_match_slices() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+        for _ in 0..2000 {
+            let data = rng.data();
+            let tree = ChthollyTree::from_iter(data.iter().copied());
+            let bounds = rng.any_bounds(data.len());
+
+            let expected = panic::catch_unwind(|| data[bounds].iter().sum::<i64>());
+            let got = panic::catch_unwind(|| tree.range_sum(bounds));
+            assert_eq!(got.ok(), expected.ok(), "{:?} on {:?}", bounds, data);
+
+            let mut tree = ChthollyTree::from_iter(data.iter().copied());
+            let got = panic::catch_unwind(AssertUnwindSafe(|| tree.assign(-1, bounds)));
+            assert_eq!(got.is_ok(), data.get(bounds).is_some());
+        }
+    }
+
+    #[test]
+    fn against_vec() {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        for _ in 0..200 {
+            let mut model = rng.data();
+            let mut tree = ChthollyTree::from_iter(model.iter().copied());
+            for _ in 0..20 {
+                let bounds = rng.valid_bounds(model.len());
+                let val = rng.below(4) as i64;
+                match rng.below(11) {
+                    0 => {
+                        tree.assign(val, bounds);
+                        model[bounds].iter_mut().for_each(|x| *x = val);
+                    }
+                    1 => {
+                        tree.assign_merge(val, bounds);
+                        model[bounds].iter_mut().for_each(|x| *x = val);
+                    }
+                    2 => {
+                        tree.map_range(|x| *x += val, bounds);
+                        model[bounds].iter_mut().for_each(|x| *x += val);
+                    }
+                    3 => {
+                        tree.map_range_merge(|x| *x %= 2, bounds);
+                        model[bounds].iter_mut().for_each(|x| *x %= 2);
+                    }
+                    4 => tree.split(rng.below(model.len() + 1)),
+                    5 => tree.compact(),
+                    6 => {
+                        let at = rng.below(model.len() + 1);
+                        let count = rng.below(3);
+                        tree.insert_run(at, count, val);
+                        model.splice(at..at, std::iter::repeat_n(val, count));
+                    }
+                    7 if !model.is_empty() => {
+                        let at = rng.below(model.len());
+                        assert_eq!(tree.remove(at), model.remove(at));
+                    }
+                    8 => {
+                        let drained = tree.drain(bounds);
+                        check_nodes(&drained);
+                        assert!(drained.iter().eq(model.drain(bounds).as_slice()));
+                    }
+                    9 => assert_eq!(tree.range_sum(bounds), model[bounds].iter().sum()),
+                    _ => assert_eq!(
+                        tree.fold_range(0, |acc, repeat, _| acc + repeat, bounds),
+                        model[bounds].len()
+                    ),
+                }
+                check_nodes(&tree);
+                assert_eq!(tree.iter().copied().collect::<Vec<_>>(), model);
+            }
+        }
+    }
+
+    #[test]
+    fn inclusive_end() {
+        let mut tree = ChthollyTree::from_iter([0; 6]);
+        tree.assign(1, 2..=4);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [0, 0, 1, 1, 1, 0]);
+        tree.assign(2, ..=0);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [2, 0, 1, 1, 1, 0]);
+        assert_eq!(tree.range_sum(..), 5);
+        assert_eq!(tree.range_sum(3..3), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn out_of_range() {
+        let mut tree = ChthollyTree::from_iter([0; 6]);
+        tree.assign(1, 4..7);
+    }
+
+    #[test]
+    fn try_range() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 4, 4]);
+        assert_eq!(tree.try_assign(0, 2..2), Err(ChthollyError::EmptyRange));
+        assert_eq!(
+            tree.try_map_range(|x| *x += 1, (Bound::Included(4), Bound::Excluded(3))),
+            Err(ChthollyError::ReversedRange { start: 4, end: 3 })
+        );
+        assert_eq!(
+            tree.try_fold_range(0, |acc, repeat, _| acc + repeat, 3..=6),
+            Err(ChthollyError::OutOfBounds { index: 7, len: 6 })
+        );
+        assert_eq!(
+            ChthollyTree::from_iter([1, 1, 2]).try_assign(9, 5..),
+            Err(ChthollyError::OutOfBounds { index: 5, len: 3 })
+        );
+        assert_eq!(
+            tree.try_split(7),
+            Err(ChthollyError::OutOfBounds { index: 7, len: 6 })
+        );
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [1, 1, 2, 3, 4, 4]);
+
+        assert_eq!(tree.try_assign(0, 1..3), Ok(()));
+        assert_eq!(tree.try_map_range(|x| *x += 1, 2..), Ok(()));
+        assert_eq!(tree.try_split(6), Ok(()));
+        assert_eq!(
+            tree.try_fold_range(0, |acc, repeat, val| acc + repeat * val, ..),
+            Ok(1 + 1 + 4 + 5 + 5)
+        );
+    }
+
+    #[test]
+    fn insert_run_overflow() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2]);
+        let got = panic::catch_unwind(AssertUnwindSafe(|| tree.insert_run(1, usize::MAX, 5)));
+        assert!(got.is_err());
+        check_nodes(&tree);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [1, 1, 2]);
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 3]);
+        tree.insert(1, 5);
+        tree.insert_run(6, 2, 3);
+        tree.insert(0, 1);
+        assert_eq!(
+            tree.iter().copied().collect::<Vec<_>>(),
+            [1, 1, 5, 1, 2, 3, 3, 3, 3]
+        );
+        assert_eq!(tree.remove(2), 5);
+        assert_eq!(
+            tree.inner
+                .iter()
+                .map(|(l, (r, v))| (*l, *r, *v))
+                .collect::<Vec<_>>(),
+            [(0, 3, 1), (3, 4, 2), (4, 8, 3)]
+        );
+
+        let drained = tree.drain(2..5);
+        assert_eq!(drained.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [1, 1, 3, 3, 3]);
+        assert_eq!(tree.len(), 5);
+    }
+
+    #[test]
+    fn get() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 3, 3]);
+        assert_eq!(tree.get(1), Some(&1));
+        assert_eq!(tree.get(6), None);
+        assert_eq!(tree[4], 3);
+        assert_eq!(tree.run_at(4), Some((3..6, &3)));
+        assert_eq!(tree.run_at(6), None);
+
+        *tree.get_mut(4).unwrap() = 0;
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [1, 1, 2, 3, 0, 3]);
+        assert_eq!(tree.run_at(5), Some((5..6, &3)));
+        assert_eq!(tree.get_mut(6), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn index_out_of_bounds() {
+        let tree = ChthollyTree::from_iter([1, 1, 2]);
+        let _ = tree[3];
+    }
+
+    #[test]
+    fn runs() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 3, 3]);
+        assert_eq!(
+            tree.runs().collect::<Vec<_>>(),
+            [(0..2, &1), (2..3, &2), (3..6, &3)]
+        );
+        assert_eq!(
+            tree.runs_in(1..=3).rev().collect::<Vec<_>>(),
+            [(3..4, &3), (2..3, &2), (1..2, &1)]
+        );
+        assert_eq!(tree.runs_in(4..5).collect::<Vec<_>>(), [(4..5, &3)]);
+        assert_eq!(tree.runs_in(2..2).next(), None);
+
+        tree.runs_mut().for_each(|(run, val)| *val *= run.len());
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [2, 2, 2, 9, 9, 9]);
+    }
+
+    #[test]
+    fn iter_both_ends() {
+        let data = [-1, 2, 2, 3, 0, 0, 0, -4, -4, 10, 10, 12];
+        let tree = ChthollyTree::from_iter(data);
+        assert!(tree.iter().rev().eq(data.iter().rev()));
+
+        let mut iter = tree.iter();
+        assert_eq!(iter.len(), 12);
+        assert_eq!(iter.nth(5), Some(&0));
+        assert_eq!(iter.next_back(), Some(&12));
+        assert_eq!(iter.nth_back(1), Some(&10));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next_back(), Some(&-4));
+        assert_eq!(iter.next(), Some(&-4));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let mut rng = Rng(0xdeadbeef);
+        for _ in 0..500 {
+            let data = rng.data();
+            let tree = ChthollyTree::from_iter(data.iter().copied());
+            let (mut iter, mut model) = (tree.iter(), data.iter());
+            while model.len() > 0 {
+                let n = rng.below(4);
+                match rng.below(2) {
+                    0 => assert_eq!(iter.nth(n), model.nth(n)),
+                    _ => assert_eq!(iter.nth_back(n), model.nth_back(n)),
+                }
+                assert_eq!(iter.len(), model.len());
+            }
+            assert_eq!(iter.next(), None);
+        }
+    }
+
+    #[test]
+    fn into_iter() {
+        let data = [-1, 2, 2, 3, 0, 0, 0, -4, -4, 10, 10, 12];
+        let mut tree = ChthollyTree::from_iter(data);
+
+        let mut sum = 0;
+        for x in &tree {
+            sum += x;
+        }
+        assert_eq!(sum, data.iter().sum());
+
+        for (run, val) in &mut tree {
+            *val += run.start as i32;
+        }
+        assert_eq!(tree[2], 3);
+
+        let mut iter = ChthollyTree::from_iter(data).into_iter();
+        assert_eq!(iter.len(), 12);
+        assert_eq!(iter.next_back(), Some(12));
+        assert_eq!(iter.next(), Some(-1));
+        assert!(iter.eq(data[1..11].iter().copied()));
+
+        let strings = ChthollyTree::from_iter(["a", "b", "b"].map(String::from));
+        assert_eq!(
+            strings.into_iter().rev().collect::<Vec<_>>(),
+            ["b", "b", "a"]
+        );
+    }
+
+    #[test]
+    fn iter_range() {
+        let data = [-1, 2, 2, 3, 0, 0, 0, -4, -4, 10, 10, 12];
+        let tree = ChthollyTree::from_iter(data);
+        assert!(tree.iter_range(2..8).eq(&data[2..8]));
+        assert!(tree.iter_range(5..=5).eq(&data[5..=5]));
+        assert!(tree.iter_range(..3).rev().eq(data[..3].iter().rev()));
+        assert_eq!(tree.iter_range(4..4).next(), None);
+        assert_eq!(tree.iter_range(1..7).len(), 6);
+    }
+
+    #[test]
+    fn kth() {
+        let mut rng = Rng(0x853c49e6748fea9b);
+        for _ in 0..200 {
+            let data = rng.data();
+            let tree = ChthollyTree::from_iter(data.iter().copied());
+            let bounds = rng.valid_bounds(data.len());
+            let mut sorted = data[bounds].to_vec();
+            sorted.sort_unstable();
+            for k in 0..=sorted.len() {
+                assert_eq!(tree.kth_smallest(bounds, k), sorted.get(k));
+                assert_eq!(tree.kth_largest(bounds, k), sorted.iter().rev().nth(k));
+            }
+        }
+    }
+
+    #[test]
+    fn range_pow_sum() {
+        let data = [7, 7, 7, 100, -3, -3, 12, 0, 0, 5];
+        let tree = ChthollyTree::from_iter(data);
+        for exp in [0, 1, 2, 7, 63] {
+            for modulus in [1, 2, 10, 1_000_000_007, u64::MAX] {
+                let expected = data[1..9].iter().fold(0u128, |acc, &x| {
+                    let base = (x as i128).rem_euclid(modulus as i128) as u128;
+                    let pow = (0..exp).fold(1 % modulus as u128, |p, _| p * base % modulus as u128);
+                    (acc + pow) % modulus as u128
+                });
+                assert_eq!(tree.range_pow_sum(1..9, exp, modulus) as u128, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn mod_int() {
+        type Mi = ModInt<998_244_353>;
+        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 3].map(Mi::new));
+        tree.map_range(|x| *x = x.pow(40), 1..4);
+        assert_eq!(
+            tree.range_sum(..4),
+            Mi::new(2) + Mi::new(2).pow(40) + Mi::new(3).pow(40)
+        );
+        assert_eq!(tree.sum(), tree.range_sum(..4) + Mi::new(3));
+    }
+
+    #[test]
+    fn range_extremes() {
+        let tree = ChthollyTree::from_iter([3, 1, 1, 4, 1, 5, 9, 2, 6, 9]);
+        assert_eq!(tree.range_min(..), Some(&1));
+        assert_eq!(tree.range_argmin(..), Some(1));
+        assert_eq!(tree.range_argmin(3..), Some(4));
+        assert_eq!(tree.range_max(..), Some(&9));
+        assert_eq!(tree.range_argmax(..), Some(6));
+        assert_eq!(tree.range_max(2..=5), Some(&5));
+        assert_eq!(tree.range_argmin(7..9), Some(7));
+        assert_eq!(tree.range_min(4..4), None);
+        assert_eq!(tree.range_argmax(4..4), None);
+    }
+
+    #[test]
+    fn range_arith() {
+        let mut tree = ChthollyTree::from_iter([1u8, 1, 2, 3, 4, 4]);
+        tree.range_add(2..4, 1, Checked).unwrap();
+        assert_eq!(
+            tree.runs().collect::<Vec<_>>(),
+            [(0..2, &1), (2..3, &3), (3..6, &4)]
+        );
+        tree.range_affine(.., 2, 1, Checked).unwrap();
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [3, 3, 7, 9, 9, 9]);
+
+        assert_eq!(
+            tree.range_mul(1..3, 100, Checked),
+            Err(ChthollyError::Overflow)
+        );
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [3, 3, 7, 9, 9, 9]);
+        assert_eq!(tree.runs().count(), 3);
+
+        tree.range_mul(1..3, 100, Saturating).unwrap();
+        assert_eq!(
+            tree.iter().copied().collect::<Vec<_>>(),
+            [3, 255, 255, 9, 9, 9]
+        );
+        tree.range_add(.., 250, Wrapping).unwrap();
+        assert_eq!(
+            tree.iter().copied().collect::<Vec<_>>(),
+            [253, 249, 249, 3, 3, 3]
+        );
+        assert_eq!(tree.runs().count(), 3);
+
+        assert_eq!(tree.range_add(2..2, 1, Checked), Ok(()));
+        assert_eq!(
+            tree.range_add(2..9, 1, Checked),
+            Err(ChthollyError::OutOfBounds { index: 9, len: 6 })
+        );
+        assert_eq!(
+            tree.range_mul((Bound::Included(4), Bound::Excluded(2)), 1, Wrapping),
+            Err(ChthollyError::ReversedRange { start: 4, end: 2 })
+        );
+    }
+
+    #[test]
+    fn policy_sums() {
+        let mut tree = ChthollyTree::from_iter([0u8; 300]);
+        assert_eq!(tree.checked_sum(), Some(0));
+        tree.assign(1, 100..);
+        assert_eq!(tree.checked_sum(), Some(200));
+        tree.assign(2, ..);
+        assert_eq!(tree.checked_sum(), None);
+        assert_eq!(tree.wrapping_sum(), (600 % 256) as u8);
+        assert_eq!(tree.saturating_sum(), u8::MAX);
+        assert_eq!(tree.sum_as::<u64>(), 600);
+        assert_eq!(tree.checked_range_sum(10..100), Some(180));
+        assert_eq!(tree.checked_range_sum(..), None);
+        assert_eq!(tree.checked_range_sum(5..5), Some(0));
+        assert_eq!(tree.wrapping_range_sum(100..), (400 % 256) as u8);
+        assert_eq!(tree.saturating_range_sum(1..), u8::MAX);
+
+        let tree = ChthollyTree::from_iter([-100i8, -100, -100, 3]);
+        assert_eq!(tree.checked_sum(), None);
+        assert_eq!(tree.wrapping_sum(), (-297i32) as i8);
+        assert_eq!(
+            tree.saturating_sum(),
+            [-100i8, -100, -100, 3]
+                .into_iter()
+                .fold(0, i8::saturating_add)
+        );
+        assert_eq!(tree.sum_as::<i128>(), -297);
+        assert_eq!(tree.sum_as::<f64>(), -297.0);
+
+        let tree = ChthollyTree::from_iter([-100i8, 50, 50, 50]);
+        assert_eq!(tree.checked_sum(), Some(50));
+        assert_eq!(tree.checked_range_sum(1..), None);
+        let tree = ChthollyTree::from_iter([-100i8, 100, 100]);
+        assert_eq!(tree.checked_sum(), Some(100));
+
+        let mut rng = Rng(0xbf58476d1ce4e5b9);
+        for _ in 0..500 {
+            let data = rng
+                .data()
+                .into_iter()
+                .map(|x| (x as i8 - 1) * 45)
+                .collect::<Vec<_>>();
+            let tree = ChthollyTree::from_iter(data.iter().copied());
+            let bounds = rng.valid_bounds(data.len());
+            assert_eq!(
+                tree.checked_range_sum(bounds),
+                data[bounds]
+                    .iter()
+                    .try_fold(0i8, |acc, x| acc.checked_add(*x)),
+                "{:?} {:?}",
+                bounds,
+                data
+            );
+        }
+    }
+
+    #[test]
+    fn eq_across_splits() {
+        fn hash(tree: &ChthollyTree<i64>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            tree.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let mut rng = Rng(0x853c49e6748fea9b);
+        for _ in 0..500 {
+            let (a, b) = (rng.data(), rng.data());
+            let mut x = ChthollyTree::from_iter(a.iter().copied());
+            let mut y = ChthollyTree::from_iter(b.iter().copied());
+            for _ in 0..3 {
+                x.split(rng.below(a.len() + 1));
+                y.split(rng.below(b.len() + 1));
+            }
+            assert_eq!(x == y, a == b, "{:?} {:?}", a, b);
+            assert_eq!(x.cmp(&y), a.cmp(&b), "{:?} {:?}", a, b);
+            assert_eq!(x.partial_cmp(&y), a.partial_cmp(&b));
+
+            let clone = x.clone();
+            x.compact();
+            assert_eq!(x, clone);
+            assert_eq!(hash(&x), hash(&clone));
+            if a == b {
+                assert_eq!(hash(&x), hash(&y));
+            }
+        }
+    }
+
+    #[test]
+    fn search() {
+        let tree = ChthollyTree::from_iter([3, 1, 1, 4, 1, 5, 9, 9, 2, 6]);
+        assert_eq!(tree.count(&1), 3);
+        assert_eq!(tree.count(&7), 0);
+        assert_eq!(tree.count_in(2..8, &1), 2);
+        assert_eq!(tree.count_in(7..=7, &9), 1);
+
+        let mut calls = 0;
+        assert_eq!(
+            tree.position(|x| {
+                calls += 1;
+                *x > 4
+            }),
+            Some(5)
         );
+        assert_eq!(calls, 5);
+        assert_eq!(tree.rposition(|x| *x == 1), Some(4));
+        assert_eq!(tree.rposition(|x| *x == 9), Some(7));
+        assert_eq!(tree.position(|x| *x == 0), None);
+        assert_eq!(tree.find_run(|x| *x == 9), Some((6..8, &9)));
+        assert_eq!(tree.find_run(|x| *x == 1), Some((1..3, &1)));
     }
 }
@@ -0,0 +1,211 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, Sub, SubAssign};
+
+use num_traits::{Num, NumCast, One, ToPrimitive, Zero};
+
+/// computes `base ^ exp mod modulus` by repeated squaring
+pub(crate) fn pow_mod(base: u64, mut exp: u64, modulus: u64) -> u64 {
+    let m = modulus as u128;
+    let mut base = base as u128 % m;
+    let mut acc = 1 % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            acc = acc * base % m;
+        }
+        base = base * base % m;
+        exp >>= 1;
+    }
+    acc as u64
+}
+
+/// An integer modulo `M`
+///
+/// It implements [`Num`] and [`NumCast`], so it works with
+/// [`range_sum`](crate::ChthollyTree::range_sum) and friends. Division multiplies by the
+/// inverse from Fermat's little theorem, so it is only meaningful when `M` is prime;
+/// the remainder of a division is then always zero.
+///
+/// `M` must be greater than 1, which every constructor checks at compile time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModInt<const M: u64>(u64);
+
+impl<const M: u64> ModInt<M> {
+    const VALID_MODULUS: () = assert!(M > 1, "the modulus of a ModInt must be greater than 1");
+
+    pub const fn new(value: u64) -> Self {
+        let () = Self::VALID_MODULUS;
+        Self(value % M)
+    }
+
+    /// the representative in `0..M`
+    pub const fn value(self) -> u64 {
+        let () = Self::VALID_MODULUS;
+        self.0
+    }
+
+    pub fn pow(self, exp: u64) -> Self {
+        let () = Self::VALID_MODULUS;
+        Self(pow_mod(self.0, exp, M))
+    }
+
+    /// the multiplicative inverse, assuming `M` is prime
+    ///
+    /// # Panic
+    ///
+    /// panic if `self` is zero
+    pub fn inv(self) -> Self {
+        assert!(self.0 != 0, "attempt to divide by zero");
+        self.pow(M - 2)
+    }
+}
+
+impl<const M: u64> From<u64> for ModInt<M> {
+    fn from(value: u64) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<const M: u64> fmt::Display for ModInt<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<const M: u64> Add for ModInt<M> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(((self.0 as u128 + rhs.0 as u128) % M as u128) as u64)
+    }
+}
+
+impl<const M: u64> Sub for ModInt<M> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<const M: u64> Mul for ModInt<M> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self((self.0 as u128 * rhs.0 as u128 % M as u128) as u64)
+    }
+}
+
+impl<const M: u64> Div for ModInt<M> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.inv()
+    }
+}
+
+impl<const M: u64> Rem for ModInt<M> {
+    type Output = Self;
+
+    fn rem(self, rhs: Self) -> Self {
+        assert!(
+            rhs.0 != 0,
+            "attempt to calculate the remainder with a divisor of zero"
+        );
+        Self(0)
+    }
+}
+
+impl<const M: u64> Neg for ModInt<M> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self((M - self.0) % M)
+    }
+}
+
+macro_rules! impl_assign {
+    ($($trait:ident $method:ident $op:tt),*) => {
+        $(
+            impl<const M: u64> $trait for ModInt<M> {
+                fn $method(&mut self, rhs: Self) {
+                    *self = *self $op rhs;
+                }
+            }
+        )*
+    };
+}
+
+impl_assign!(AddAssign add_assign +, SubAssign sub_assign -, MulAssign mul_assign *, DivAssign div_assign /);
+
+impl<const M: u64> Default for ModInt<M> {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl<const M: u64> Zero for ModInt<M> {
+    fn zero() -> Self {
+        Self::new(0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl<const M: u64> One for ModInt<M> {
+    fn one() -> Self {
+        Self::new(1)
+    }
+}
+
+impl<const M: u64> Num for ModInt<M> {
+    type FromStrRadixErr = std::num::ParseIntError;
+
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        u64::from_str_radix(str, radix).map(Self::new)
+    }
+}
+
+impl<const M: u64> ToPrimitive for ModInt<M> {
+    fn to_i64(&self) -> Option<i64> {
+        self.0.to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        Some(self.0)
+    }
+}
+
+impl<const M: u64> NumCast for ModInt<M> {
+    /// reduces `n` modulo `M`, negative values wrap around to `M - |n| % M`
+    fn from<N: ToPrimitive>(n: N) -> Option<Self> {
+        let () = Self::VALID_MODULUS;
+        match n.to_i128() {
+            Some(n) => Some(Self(n.rem_euclid(M as i128) as u64)),
+            None => n.to_u128().map(|n| Self((n % M as u128) as u64)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ModInt;
+    use num_traits::NumCast;
+
+    type Mi = ModInt<1_000_000_007>;
+
+    #[test]
+    fn arith() {
+        let a = Mi::new(1_000_000_006);
+        assert_eq!(a + Mi::new(2), Mi::new(1));
+        assert_eq!(Mi::new(1) - Mi::new(2), a);
+        assert_eq!(a * a, Mi::new(1));
+        assert_eq!(Mi::new(6) / Mi::new(3), Mi::new(2));
+        assert_eq!(Mi::new(3) * Mi::new(3).inv(), Mi::new(1));
+        assert_eq!(Mi::new(2).pow(30), Mi::new(1 << 30));
+        assert_eq!(<Mi as NumCast>::from(-1i32), Some(a));
+        assert_eq!(<Mi as NumCast>::from(2_000_000_016u64), Some(Mi::new(2)));
+    }
+}
@@ -0,0 +1,168 @@
+use std::ops::{Deref, Range, RangeBounds};
+
+use crate::ChthollyTree;
+
+/// Receives notifications about structural changes of a [`ChthollyTree`]
+///
+/// Every method does nothing by default. `()` ignores all events, and a closure
+/// `FnMut(Range<usize>, T)` only observes overwrites.
+pub trait Observer<T> {
+    /// a node `[l, r)` was split into `[l, at)` and `[at, r)`
+    fn on_split(&mut self, _at: usize) {}
+
+    /// the node starting at `at` was merged into the node before it
+    fn on_merge(&mut self, _at: usize) {}
+
+    /// `value` was removed from `range` by an assignment
+    fn on_overwrite(&mut self, _range: Range<usize>, _value: T) {}
+
+    /// `value` is about to be pushed at position `at`
+    fn on_push(&mut self, _at: usize, _value: &T) {}
+}
+
+impl<T> Observer<T> for () {}
+
+impl<T, F: FnMut(Range<usize>, T)> Observer<T> for F {
+    fn on_overwrite(&mut self, range: Range<usize>, value: T) {
+        self(range, value)
+    }
+}
+
+/// A [`ChthollyTree`] that reports every split, merge, overwrite and push to an [`Observer`]
+///
+/// The tree can only be changed through the methods below, which pass the observer along,
+/// and is readable through `Deref`.
+#[derive(Debug, Default)]
+pub struct ObservedTree<T, O> {
+    tree: ChthollyTree<T>,
+    observer: O,
+}
+
+impl<T, O: Observer<T>> ObservedTree<T, O> {
+    pub fn new(tree: ChthollyTree<T>, observer: O) -> Self {
+        Self { tree, observer }
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    pub fn into_parts(self) -> (ChthollyTree<T>, O) {
+        (self.tree, self.observer)
+    }
+}
+
+impl<T, O> Deref for ObservedTree<T, O> {
+    type Target = ChthollyTree<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tree
+    }
+}
+
+impl<T: Eq, O: Observer<T>> ObservedTree<T, O> {
+    pub fn push(&mut self, value: T) {
+        self.tree.push_observed(value, &mut self.observer);
+    }
+}
+
+impl<T: Clone, O: Observer<T>> ObservedTree<T, O> {
+    /// see [`ChthollyTree::split`]
+    pub fn split(&mut self, at: usize) {
+        if let Err(e) = self.tree.split_observed(at, &mut self.observer) {
+            panic!("{}", e);
+        }
+    }
+
+    /// see [`ChthollyTree::assign`]
+    pub fn assign(&mut self, val: T, range: impl RangeBounds<usize>) {
+        self.tree.assign_observed(val, range, &mut self.observer);
+    }
+
+    /// see [`ChthollyTree::map_range`]
+    pub fn map_range(&mut self, f: impl Fn(&mut T), range: impl RangeBounds<usize>) {
+        self.tree.map_range_observed(f, range, &mut self.observer);
+    }
+}
+
+impl<T: Clone + Eq, O: Observer<T>> ObservedTree<T, O> {
+    /// see [`ChthollyTree::assign_merge`]
+    pub fn assign_merge(&mut self, val: T, range: impl RangeBounds<usize>) {
+        self.tree
+            .assign_merge_observed(val, range, &mut self.observer);
+    }
+
+    /// see [`ChthollyTree::map_range_merge`]
+    pub fn map_range_merge(&mut self, f: impl Fn(&mut T), range: impl RangeBounds<usize>) {
+        self.tree
+            .map_range_merge_observed(f, range, &mut self.observer);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Range;
+
+    use super::{ObservedTree, Observer};
+    use crate::ChthollyTree;
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Log(Vec<String>);
+
+    impl Observer<i32> for Log {
+        fn on_split(&mut self, at: usize) {
+            self.0.push(format!("split {}", at));
+        }
+
+        fn on_merge(&mut self, at: usize) {
+            self.0.push(format!("merge {}", at));
+        }
+
+        fn on_overwrite(&mut self, range: Range<usize>, value: i32) {
+            self.0.push(format!("overwrite {:?} {}", range, value));
+        }
+
+        fn on_push(&mut self, at: usize, value: &i32) {
+            self.0.push(format!("push {} {}", at, value));
+        }
+    }
+
+    #[test]
+    fn observed() {
+        let mut tree = ObservedTree::new(ChthollyTree::new(), Log::default());
+        tree.push(1);
+        tree.push(1);
+        tree.push(2);
+        tree.assign(3, 1..3);
+        tree.assign_merge(3, ..1);
+        tree.map_range(|x| *x += 1, 2..);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [3, 3, 4]);
+        assert_eq!(
+            tree.observer().0,
+            [
+                "push 0 1",
+                "push 1 1",
+                "push 2 2",
+                "split 1",
+                "overwrite 1..2 1",
+                "overwrite 2..3 2",
+                "overwrite 0..1 1",
+                "merge 1",
+                "split 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn assign_with() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 3, 3]);
+        let mut evicted = vec![];
+        tree.assign_with(0, 1..5, |range, val| evicted.push((range, val)));
+        assert_eq!(evicted, [(1..2, 1), (2..3, 2), (3..5, 3)]);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [1, 0, 0, 0, 0, 3]);
+    }
+}
@@ -0,0 +1,126 @@
+//! Serializes a tree as its list of nodes, `[[len, value], ...]`, instead of the
+//! expanded sequence
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ChthollyTree;
+
+impl<T: Serialize> Serialize for ChthollyTree<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.inner.len()))?;
+        for (run, val) in self.runs() {
+            seq.serialize_element(&(run.len(), val))?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for ChthollyTree<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(RunsVisitor(PhantomData))
+    }
+}
+
+struct RunsVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for RunsVisitor<T> {
+    type Value = ChthollyTree<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of [length, value] pairs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut inner = BTreeMap::new();
+        let mut len = 0usize;
+        while let Some((count, val)) = seq.next_element::<(usize, T)>()? {
+            if count == 0 {
+                return Err(A::Error::custom(format!("empty run at index {}", len)));
+            }
+            let end = len
+                .checked_add(count)
+                .ok_or_else(|| A::Error::custom("total length overflows usize"))?;
+            inner.insert(len, (end, val));
+            len = end;
+        }
+        Ok(ChthollyTree { inner, len })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+    use crate::ChthollyTree;
+
+    /// compares the nodes themselves rather than the elements
+    #[derive(Debug)]
+    struct Nodes(ChthollyTree<i32>);
+
+    impl PartialEq for Nodes {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.len() == other.0.len() && self.0.runs().eq(other.0.runs())
+        }
+    }
+
+    impl Serialize for Nodes {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Nodes {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            ChthollyTree::deserialize(deserializer).map(Nodes)
+        }
+    }
+
+    fn run(len: u64, val: i32) -> [Token; 4] {
+        [
+            Token::Tuple { len: 2 },
+            Token::U64(len),
+            Token::I32(val),
+            Token::TupleEnd,
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut tree = ChthollyTree::from_iter([1, 1, 2, 3, 3, 3]);
+        tree.split(4);
+        let mut tokens = vec![Token::Seq { len: Some(4) }];
+        tokens.extend(run(2, 1));
+        tokens.extend(run(1, 2));
+        tokens.extend(run(1, 3));
+        tokens.extend(run(2, 3));
+        tokens.push(Token::SeqEnd);
+        assert_tokens(&Nodes(tree), &tokens);
+
+        assert_tokens(
+            &Nodes(ChthollyTree::new()),
+            &[Token::Seq { len: Some(0) }, Token::SeqEnd],
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let mut tokens = vec![Token::Seq { len: Some(2) }];
+        tokens.extend(run(2, 1));
+        tokens.extend(run(0, 2));
+        tokens.push(Token::SeqEnd);
+        assert_de_tokens_error::<Nodes>(&tokens, "empty run at index 2");
+
+        let mut tokens = vec![Token::Seq { len: Some(2) }];
+        tokens.extend(run(u64::MAX, 1));
+        tokens.extend(run(1, 2));
+        tokens.push(Token::SeqEnd);
+        assert_de_tokens_error::<Nodes>(&tokens, "total length overflows usize");
+    }
+}